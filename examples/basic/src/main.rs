@@ -20,21 +20,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
 
     // Add some commands
-    shell
-        .commands
-        .insert("greet", Command::new("greets you.".to_string(), greet));
+    shell.commands.insert(
+        "greet".into(),
+        Command::new("greets you.".to_string(), greet),
+    );
 
-    shell
-        .commands
-        .insert("echo", Command::new("prints the input.".to_string(), echo));
+    shell.commands.insert(
+        "echo".into(),
+        Command::new("prints the input.".to_string(), echo),
+    );
 
     shell.commands.insert(
-        "count",
+        "count".into(),
         Command::new("increments a counter.".to_string(), count),
     );
 
     shell.commands.insert(
-        "cat",
+        "cat".into(),
         Command::new_async(
             "Displays a plaintext file.".to_string(),
             async_fn!(u64, cat),
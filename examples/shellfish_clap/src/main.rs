@@ -41,10 +41,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
     shell
         .commands
-        .insert("greet", clap_command!((), GreetArgs, greet));
+        .insert("greet".into(), clap_command!((), GreetArgs, greet));
     shell
         .commands
-        .insert("cat", clap_command!((), CatArgs, async cat));
+        .insert("cat".into(), clap_command!((), CatArgs, async cat));
     shell.run_async().await?;
 
     Ok(())
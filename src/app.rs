@@ -16,16 +16,41 @@ use std::error::Error;
 use std::fmt::Display;
 use std::fs;
 use std::io::{Read, Write};
+use std::sync::Arc;
 
 #[cfg(feature = "async-std")]
 use async_std::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::builtin::{self, Action, Builtins};
+use crate::command::Commands;
+#[cfg(feature = "async")]
+use crate::executor::{BlockOn, Executor};
 pub use crate::handler::app::{CommandLineHandler, DefaultCommandLineHandler};
 #[cfg(feature = "async")]
 use crate::handler::async_app::DefaultAsyncCLIHandler;
+use crate::help::DEFAULT_WIDTH;
+use crate::input_handler::{Input, IO};
+use crate::reporter::DefaultReporter;
+use crate::shell::{escape, unescape, Outcome};
+use crate::sink::Sink;
 use crate::*;
 
+/// Adapts a shell's built ins to an app. Unlike in a shell, quitting
+/// deletes the saved state, so built ins which quit with the default help
+/// say so. Any others are kept as they are.
+fn for_app(mut builtins: Builtins<'_>) -> Builtins<'_> {
+    let defaults = builtin::defaults();
+    let default = |help: &str| defaults.values().any(|d| d.help == help);
+    for builtin in builtins.values_mut() {
+        if builtin.action == Action::Quit && default(&builtin.help) {
+            builtin.help =
+                "deletes all temporary state information.".to_string();
+        }
+    }
+    builtins
+}
+
 /// See the module level dicumentation. Note `App` closely mirrors state and
 /// so can be created from it (given the right trait bounds)
 pub struct App<
@@ -33,10 +58,36 @@ pub struct App<
     T: Serialize + for<'a> Deserialize<'a>,
     H: CommandLineHandler,
 > {
-    pub commands: IndexMap<&'b str, Command<T>>,
+    pub commands: Commands<'b, T>,
+    /// The commands built in to the handler, such as `help`. See the
+    /// [`builtin`](crate::builtin) module for more.
+    pub builtins: Builtins<'b>,
     pub state: T,
     pub handler: H,
     pub description: String,
+    /// Middleware which is run around every command. See the
+    /// [`middleware`](crate::middleware) module for more.
+    pub middleware: Vec<Arc<dyn Middleware<T>>>,
+    /// Whether commands are run without being confirmed. Questions are
+    /// otherwise asked on the standard input.
+    pub batch: bool,
+    /// Where the output of built ins and commands goes. This is the
+    /// standard output by default. See the [`sink`](crate::sink) module
+    /// for more.
+    pub output: Sink,
+    /// Where errors go. This is the standard error by default.
+    pub error: Sink,
+    /// What is told about anything which goes wrong, such as a command
+    /// failing. See the [`reporter`](crate::reporter) module for more.
+    pub reporter: Arc<dyn Reporter>,
+    /// The colours used, which by default are only used on terminals.
+    /// See the [`theme`](crate::theme) module for more.
+    pub theme: Theme,
+    /// Runs asynchronous commands when the shell is run synchronously.
+    /// See the [`executor`](crate::executor) module for more.
+    #[cfg(feature = "async")]
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    pub executor: Arc<dyn Executor>,
 }
 
 impl<
@@ -53,9 +104,18 @@ impl<
     fn try_from(shell: Shell<'f, T, M, H, I>) -> Result<Self, Box<dyn Error>> {
         let mut this = Self {
             commands: shell.commands,
+            builtins: for_app(shell.builtins),
             state: shell.state,
-            handler: DefaultCommandLineHandler { proj_name: None },
+            handler: DefaultCommandLineHandler::default(),
             description: shell.description,
+            middleware: shell.middleware,
+            batch: shell.batch,
+            output: shell.output,
+            error: shell.error,
+            reporter: shell.reporter,
+            theme: shell.theme,
+            #[cfg(feature = "async")]
+            executor: shell.executor,
         };
         this.load_cache()?;
         Ok(this)
@@ -71,9 +131,18 @@ impl<'f: 't, 't, T: Serialize + for<'a> Deserialize<'a> + Send>
     ) -> Result<Self, Box<dyn Error>> {
         let mut this = Self {
             commands: shell.commands,
+            builtins: for_app(shell.builtins),
             state: shell.state,
-            handler: DefaultAsyncCLIHandler { proj_name: None },
+            handler: DefaultAsyncCLIHandler::default(),
             description: shell.description,
+            middleware: shell.middleware,
+            batch: shell.batch,
+            output: shell.output,
+            error: shell.error,
+            reporter: shell.reporter,
+            theme: shell.theme,
+            #[cfg(feature = "async")]
+            executor: shell.executor,
         };
         this.load_cache()?;
         Ok(this)
@@ -87,11 +156,21 @@ impl<T: Serialize + for<'a> Deserialize<'a>>
     pub fn new(state: T, project_name: String) -> Result<Self, Box<dyn Error>> {
         let mut this = Self {
             commands: IndexMap::new(),
+            builtins: for_app(builtin::defaults()),
             state,
             handler: DefaultCommandLineHandler {
                 proj_name: Some(project_name),
+                ..Default::default()
             },
             description: String::new(),
+            middleware: Vec::new(),
+            batch: false,
+            output: Sink::stdout(),
+            error: Sink::stderr(),
+            reporter: Arc::new(DefaultReporter),
+            theme: Theme::default(),
+            #[cfg(feature = "async")]
+            executor: Arc::new(BlockOn),
         };
         this.load_cache()?;
         Ok(this)
@@ -111,9 +190,18 @@ impl<T: Serialize + for<'a> Deserialize<'a>, H: CommandLineHandler>
     ) -> Result<Self, Box<dyn Error>> {
         let mut this = Self {
             commands: IndexMap::new(),
+            builtins: for_app(builtin::defaults()),
             state,
             handler,
             description: String::new(),
+            middleware: Vec::new(),
+            batch: false,
+            output: Sink::stdout(),
+            error: Sink::stderr(),
+            reporter: Arc::new(DefaultReporter),
+            theme: Theme::default(),
+            #[cfg(feature = "async")]
+            executor: Arc::new(BlockOn),
         };
         this.load_cache()?;
         Ok(this)
@@ -146,12 +234,20 @@ impl<
     ///
     /// Returns a bool on wether we have 'quit' or not
     pub fn run_vec(&mut self, vec: Vec<String>) -> std::io::Result<bool> {
-        let result = self.handler.handle(
+        let (mut output, mut error) = (self.output.clone(), self.error.clone());
+        let theme = self.theme.resolve(self.output.is_terminal());
+        let error_theme = self.theme.resolve(self.error.is_terminal());
+        let width = self.output.width();
+        let (result, _) = self.handle(
             vec,
-            &self.commands,
-            &mut self.state,
-            &self.description,
+            &mut output,
+            &mut error,
+            &theme,
+            &error_theme,
+            width,
         );
+        self.output.flush()?;
+        self.error.flush()?;
 
         // Do stuff with the cache
         match result {
@@ -184,6 +280,73 @@ impl<
     pub fn run_args(&mut self) -> std::io::Result<bool> {
         self.run_vec(env::args().collect())
     }
+
+    /// Runs a single line of arguments, which doesn't include the binary
+    /// name, and returns what it wrote. Unlike
+    /// [`run_vec`](Self::run_vec), the cache is neither read nor
+    /// written.
+    pub fn execute(&mut self, line: &str) -> Outcome {
+        let (mut output, mut error) = (Vec::new(), Vec::new());
+        let theme = self.theme.resolve(false);
+        let mut vec = match unescape(line.trim()) {
+            Ok(vec) => vec,
+            Err(e) => {
+                let _ =
+                    self.reporter.invalid_line(&mut error, &theme, line, &e);
+                return Outcome::new(false, false, output, error);
+            }
+        };
+        vec.insert(0, env::args().next().unwrap_or_default());
+        let (quit, success) = self.handle(
+            vec,
+            &mut output,
+            &mut error,
+            &theme,
+            &theme,
+            DEFAULT_WIDTH,
+        );
+        Outcome::new(quit, success, output, error)
+    }
+
+    /// Handles a vec of strings, returning whether it asked to quit and
+    /// whether it succeeded.
+    fn handle(
+        &mut self,
+        vec: Vec<String>,
+        output: &mut (dyn Write + Send),
+        error: &mut (dyn Write + Send),
+        theme: &Theme,
+        error_theme: &Theme,
+        width: usize,
+    ) -> (bool, bool) {
+        let line: Vec<String> =
+            vec.iter().skip(1).map(|arg| escape(arg)).collect();
+        let line = line.join(" ");
+        let mut input = IO;
+        let mut context = Context {
+            builtins: &self.builtins,
+            commands: &mut self.commands,
+            state: &mut self.state,
+            description: &self.description,
+            middleware: &self.middleware,
+            line: &line,
+            output,
+            error,
+            input_handler: Input::local(&mut input),
+            batch: self.batch,
+            abbreviations: false,
+            reporter: &*self.reporter,
+            theme,
+            error_theme,
+            width,
+            #[cfg(feature = "async")]
+            executor: &*self.executor,
+            transitions: Vec::new(),
+            failed: false,
+        };
+        let quit = self.handler.handle(vec, &mut context);
+        (quit, !context.failed)
+    }
 }
 
 #[cfg(feature = "async")]
@@ -199,15 +362,22 @@ impl<
         &mut self,
         vec: Vec<String>,
     ) -> std::io::Result<bool> {
-        let result = self
-            .handler
+        let (mut output, mut error) = (self.output.clone(), self.error.clone());
+        let theme = self.theme.resolve(self.output.is_terminal());
+        let error_theme = self.theme.resolve(self.error.is_terminal());
+        let width = self.output.width();
+        let (result, _) = self
             .handle_async(
                 vec,
-                &self.commands,
-                &mut self.state,
-                &self.description,
+                &mut output,
+                &mut error,
+                &theme,
+                &error_theme,
+                width,
             )
             .await;
+        self.output.flush()?;
+        self.error.flush()?;
 
         // Do stuff with the cache
         match result {
@@ -274,4 +444,98 @@ impl<
     pub async fn run_args_async(&mut self) -> std::io::Result<bool> {
         self.run_vec_async(env::args().collect()).await
     }
+
+    /// Runs a single line of arguments asynchronously, see
+    /// [`execute`](Self::execute).
+    pub async fn execute_async(&mut self, line: &str) -> Outcome {
+        let (mut output, mut error) = (Vec::new(), Vec::new());
+        let theme = self.theme.resolve(false);
+        let mut vec = match unescape(line.trim()) {
+            Ok(vec) => vec,
+            Err(e) => {
+                let _ =
+                    self.reporter.invalid_line(&mut error, &theme, line, &e);
+                return Outcome::new(false, false, output, error);
+            }
+        };
+        vec.insert(0, env::args().next().unwrap_or_default());
+        let (quit, success) = self
+            .handle_async(
+                vec,
+                &mut output,
+                &mut error,
+                &theme,
+                &theme,
+                DEFAULT_WIDTH,
+            )
+            .await;
+        Outcome::new(quit, success, output, error)
+    }
+
+    /// Handles a vec of strings asynchronously, returning whether it asked
+    /// to quit and whether it succeeded.
+    async fn handle_async(
+        &mut self,
+        vec: Vec<String>,
+        output: &mut (dyn Write + Send),
+        error: &mut (dyn Write + Send),
+        theme: &Theme,
+        error_theme: &Theme,
+        width: usize,
+    ) -> (bool, bool) {
+        let line: Vec<String> =
+            vec.iter().skip(1).map(|arg| escape(arg)).collect();
+        let line = line.join(" ");
+        let mut input = IO;
+        let mut context = Context {
+            builtins: &self.builtins,
+            commands: &mut self.commands,
+            state: &mut self.state,
+            description: &self.description,
+            middleware: &self.middleware,
+            line: &line,
+            output,
+            error,
+            input_handler: Input::new(&mut input),
+            batch: self.batch,
+            abbreviations: false,
+            reporter: &*self.reporter,
+            theme,
+            error_theme,
+            width,
+            #[cfg(feature = "async")]
+            executor: &*self.executor,
+            transitions: Vec::new(),
+            failed: false,
+        };
+        let quit = self.handler.handle_async(vec, &mut context).await;
+        (quit, !context.failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builtin;
+
+    #[test]
+    fn keeps_the_shells_built_ins() {
+        let mut builtins = builtin::defaults();
+        builtins.shift_remove("exit");
+        builtins.insert(
+            "bye".into(),
+            Builtin::new(Action::Quit).with_help("au revoir.".to_string()),
+        );
+        builtins.insert("echo".into(), Builtin::new(Action::Echo));
+
+        let builtins = for_app(builtins);
+        let names: Vec<_> = builtins.keys().map(|name| &**name).collect();
+        assert_eq!(names, ["help", "quit", "bye", "echo"]);
+        assert_eq!(
+            builtins["quit"].help,
+            "deletes all temporary state information."
+        );
+        assert_eq!(builtins["bye"].help, "au revoir.");
+        assert_eq!(builtins["echo"].help, "displays its arguments.");
+    }
 }
@@ -0,0 +1,107 @@
+//! # Built-ins
+//!
+//! Built-ins are the commands which the handlers provide themselves, rather
+//! than running a [`Command`](crate::Command). A shell starts with `help`,
+//! `quit` and `exit`, but they are kept by name in
+//! [`Shell::builtins`](crate::Shell::builtins) so can be renamed, removed,
+//! or have their help changed. Others can be added, and a built-in can be
+//! replaced by removing it and adding a command of the same name.
+//!
+//! ```rust
+//! use shellfish::builtin::{Action, Builtin};
+//! use shellfish::Shell;
+//!
+//! let mut shell = Shell::new((), "[Shell]-$");
+//!
+//! // Only keep `quit`, in French
+//! shell.builtins.shift_remove("exit");
+//! shell.builtins.insert(
+//!     "aide".into(),
+//!     Builtin::new(Action::Help).with_help("affiche l'aide.".to_string()),
+//! );
+//! shell.builtins.shift_remove("help");
+//!
+//! // Add some of the optional ones
+//! shell.builtins.insert("clear".into(), Builtin::new(Action::Clear));
+//! shell.builtins.insert(
+//!     "version".into(),
+//!     Builtin::new(Action::Version(env!("CARGO_PKG_VERSION").to_string())),
+//! );
+//! ```
+
+use indexmap::IndexMap;
+use std::borrow::Cow;
+
+/// The built-ins of a shell, by name.
+pub type Builtins<'a> = IndexMap<Cow<'a, str>, Builtin>;
+
+/// What a built-in does.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Action {
+    /// Displays the help. Given a category, such as `help session`, it
+    /// displays just the commands within it, and `help -s <keywords>`
+    /// searches the commands.
+    Help,
+    /// Quits the shell.
+    Quit,
+    /// Clears the screen.
+    Clear,
+    /// Displays the lines which have been entered, if the
+    /// [`InputHandler`](crate::InputHandler) keeps them.
+    History,
+    /// Displays the given version.
+    Version(String),
+    /// Displays its arguments.
+    Echo,
+}
+
+impl Action {
+    /// The help shown for this action, unless it is changed.
+    fn help(&self) -> &'static str {
+        match self {
+            Action::Help => "displays help information.",
+            Action::Quit => "quits the shell.",
+            Action::Clear => "clears the screen.",
+            Action::History => "displays the command history.",
+            Action::Version(_) => "displays the version.",
+            Action::Echo => "displays its arguments.",
+        }
+    }
+}
+
+/// A built-in command.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Builtin {
+    /// What it does.
+    pub action: Action,
+    /// A help string, as with [`Command::help`](crate::Command::help).
+    pub help: String,
+}
+
+impl Builtin {
+    /// Creates a new built-in with the default (English) help.
+    pub fn new(action: Action) -> Self {
+        Self {
+            help: action.help().to_string(),
+            action,
+        }
+    }
+
+    /// Sets the help string of this built-in.
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = help;
+        self
+    }
+}
+
+/// The built-ins a shell starts with: `help`, `quit` and `exit`.
+pub fn defaults() -> Builtins<'static> {
+    let mut builtins = Builtins::new();
+    builtins.insert("help".into(), Builtin::new(Action::Help));
+    builtins.insert("quit".into(), Builtin::new(Action::Quit));
+    builtins.insert(
+        "exit".into(),
+        Builtin::new(Action::Quit).with_help("exits the shell.".to_string()),
+    );
+    builtins
+}
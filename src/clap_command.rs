@@ -12,8 +12,8 @@
 ///     // - snip
 /// }
 ///
-/// shell.commands.insert("greet", clap_command!((), Args, greet));
-/// shell.commands.insert("greet-async", clap_command!((), Args, async greet_async));
+/// shell.commands.insert("greet".into(), clap_command!((), Args, greet));
+/// shell.commands.insert("greet-async".into(), clap_command!((), Args, async greet_async));
 ///
 /// fn greet(_state: &mut (), args: Args) -> Result<(), Box<dyn std::error::Error>> {
 ///     // - snip
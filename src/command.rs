@@ -1,4 +1,10 @@
+use indexmap::IndexMap;
+use std::borrow::Cow;
 use std::error::Error;
+
+use crate::completion::Completion;
+use crate::namespace::Namespace;
+use crate::Context;
 #[cfg(feature = "async")]
 use std::{future::Future, pin::Pin};
 
@@ -25,7 +31,6 @@ macro_rules! async_fn {
     }}
 }
 
-#[derive(Clone)]
 pub struct Command<T> {
     /// The function pointer which this links to.
     pub command: CommandType<T>,
@@ -35,6 +40,37 @@ pub struct Command<T> {
     /// prints the arguments to the output.
     /// ```
     pub help: String,
+    /// An optional usage string, which describes the arguments the command
+    /// takes. For example, if it was a `greet` command:
+    /// ```txt
+    /// <name> [age]
+    /// ```
+    pub usage: Option<String>,
+    /// An optional longer description of the command, which can span
+    /// several paragraphs. It is searched by `help -s`.
+    pub long_help: Option<String>,
+    /// Example lines which run the command, such as `greet John 32`. See
+    /// [`with_example`](Command::with_example).
+    pub examples: Vec<String>,
+    /// An optional function which completes the arguments of this command.
+    /// See [`with_completer`](Command::with_completer).
+    pub completer: Option<CompleteFn<T>>,
+    /// An optional function which decides whether the command can be run
+    /// given the current state. See [`with_guard`](Command::with_guard).
+    pub guard: Option<GuardFn<T>>,
+    /// Whether to leave the command out of the help whilst it is
+    /// unavailable, rather than showing it greyed out.
+    pub hide_when_unavailable: bool,
+    /// An optional question to confirm before the command is run. See
+    /// [`with_confirmation`](Command::with_confirmation).
+    pub confirmation: Option<String>,
+    /// An optional category, such as `Session`, which the help groups the
+    /// command under. See [`with_category`](Command::with_category).
+    pub category: Option<String>,
+    /// Where the command comes within its category in the help. Commands
+    /// with a higher priority come first, and those with the same priority
+    /// keep the order they were added in. This is `0` by default.
+    pub priority: i32,
 }
 
 impl<T> Command<T> {
@@ -57,7 +93,7 @@ impl<T> Command<T> {
     ///
     ///     // Creates a command
     ///     shell.commands.insert(
-    ///         "greet",
+    ///         "greet".into(),
     ///         Command::new("greets_you".to_string(), greet),
     ///     );
     /// }
@@ -66,6 +102,15 @@ impl<T> Command<T> {
         Self {
             command: CommandType::Sync(command),
             help,
+            usage: None,
+            completer: None,
+            guard: None,
+            hide_when_unavailable: false,
+            confirmation: None,
+            long_help: None,
+            examples: Vec::new(),
+            category: None,
+            priority: 0,
         }
     }
 
@@ -91,7 +136,7 @@ impl<T> Command<T> {
     ///
     ///     // Creates a command
     ///     shell.commands.insert(
-    ///         "greet",
+    ///         "greet".into(),
     ///         Command::new_async("greets_you".to_string(), async_fn!((), greet)),
     ///     );
     /// }
@@ -101,15 +146,308 @@ impl<T> Command<T> {
         Self {
             command: CommandType::Async(command),
             help,
+            usage: None,
+            completer: None,
+            guard: None,
+            hide_when_unavailable: false,
+            confirmation: None,
+            long_help: None,
+            examples: Vec::new(),
+            category: None,
+            priority: 0,
+        }
+    }
+
+    /// Creates a new `Command` which is given the whole [`Context`] rather
+    /// than just the state. This lets it change the commands whilst the
+    /// shell is running.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use shellfish::*;
+    /// use std::error::Error;
+    ///
+    /// fn hello(_state: &mut (), _args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    ///     println!("Hello!");
+    ///     Ok(())
+    /// }
+    ///
+    /// fn load(context: &mut Context<'_, '_, ()>, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    ///     // Register a command under the name given
+    ///     let name = args.get(1).ok_or("no name given")?;
+    ///     context.register(
+    ///         name.clone(),
+    ///         Command::new("says hello.".to_string(), hello),
+    ///     );
+    ///     Ok(())
+    /// }
+    ///
+    /// let mut shell = Shell::new((), "[Shell]-$");
+    /// shell.commands.insert(
+    ///     "load".into(),
+    ///     Command::new_with_context("loads a command.".to_string(), load),
+    /// );
+    /// ```
+    pub fn new_with_context(
+        help: String,
+        command: ContextCommandFn<T>,
+    ) -> Self {
+        Self {
+            command: CommandType::Context(command),
+            help,
+            usage: None,
+            completer: None,
+            guard: None,
+            hide_when_unavailable: false,
+            confirmation: None,
+            long_help: None,
+            examples: Vec::new(),
+            category: None,
+            priority: 0,
+        }
+    }
+
+    /// Creates a new `Command` which is a [`Namespace`] of other commands.
+    /// See the [`namespace`](crate::namespace) module for more.
+    pub fn new_namespace(help: String, namespace: Namespace<T>) -> Self {
+        Self {
+            command: CommandType::Namespace(namespace),
+            help,
+            usage: None,
+            completer: None,
+            guard: None,
+            hide_when_unavailable: false,
+            confirmation: None,
+            long_help: None,
+            examples: Vec::new(),
+            category: None,
+            priority: 0,
+        }
+    }
+
+    /// Sets the function which decides whether this command is available.
+    ///
+    /// Unavailable commands are not run, and are left out of completion.
+    /// The help shows them greyed out along with the reason, unless
+    /// [`hide_when_unavailable`](Command::hide_when_unavailable) is set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use shellfish::Command;
+    /// use std::error::Error;
+    ///
+    /// struct State {
+    ///     logged_in: bool,
+    /// }
+    ///
+    /// fn push(_state: &mut State, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    ///     //--snip--
+    ///     # Ok(())
+    /// }
+    ///
+    /// let command = Command::new("pushes changes.".to_string(), push)
+    ///     .with_guard(|state| match state.logged_in {
+    ///         true => Ok(()),
+    ///         false => Err("you need to log in first".to_string()),
+    ///     });
+    /// ```
+    pub fn with_guard(mut self, guard: GuardFn<T>) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    /// Checks whether this command can be run, returning the reason if it
+    /// can't.
+    pub fn available(&self, state: &T) -> Result<(), String> {
+        match self.guard {
+            Some(guard) => guard(state),
+            None => Ok(()),
+        }
+    }
+
+    /// Marks this command as dangerous, so the user is asked the given
+    /// question before it is run. For example:
+    /// ```txt
+    /// Really drop the table? [y/N]
+    /// ```
+    ///
+    /// The question is not asked if the command is given the `--yes` flag
+    /// (which is removed from its arguments), or if the shell is in batch
+    /// mode.
+    pub fn with_confirmation(mut self, question: String) -> Self {
+        self.confirmation = Some(question);
+        self
+    }
+
+    /// Sets the category of this command.
+    ///
+    /// The help shows each category as its own section, in the order the
+    /// categories first appear. Commands without one share a section, which
+    /// is placed where the first of them appears. `help <category>` shows
+    /// just that section.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use shellfish::{Command, Shell};
+    /// use std::error::Error;
+    ///
+    /// fn login(_state: &mut (), _args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    ///     //--snip--
+    ///     # Ok(())
+    /// }
+    ///
+    /// let mut shell = Shell::new((), "[Shell]-$");
+    /// shell.commands.insert(
+    ///     "login".into(),
+    ///     Command::new("logs in.".to_string(), login)
+    ///         .with_category("Session".to_string())
+    ///         .with_priority(1),
+    /// );
+    /// ```
+    pub fn with_category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    /// Sets the priority of this command, which orders it within its
+    /// category in the help. Higher priorities come first.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the longer description of this command.
+    pub fn with_long_help(mut self, long_help: String) -> Self {
+        self.long_help = Some(long_help);
+        self
+    }
+
+    /// Adds an example line which runs this command.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use shellfish::Command;
+    /// use std::error::Error;
+    ///
+    /// fn greet(_state: &mut (), args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    ///     //--snip--
+    ///     # Ok(())
+    /// }
+    ///
+    /// let command = Command::new("greets someone.".to_string(), greet)
+    ///     .with_usage("<name> [age]".to_string())
+    ///     .with_long_help("Greets someone by name, and their age if it is given.".to_string())
+    ///     .with_example("greet John".to_string())
+    ///     .with_example("greet John 32".to_string());
+    /// ```
+    pub fn with_example(mut self, example: String) -> Self {
+        self.examples.push(example);
+        self
+    }
+
+    /// Sets the usage string of this command.
+    pub fn with_usage(mut self, usage: String) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Sets the function used to complete the arguments of this command.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use shellfish::{completion, Command};
+    /// use std::error::Error;
+    ///
+    /// fn set(_state: &mut (), args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    ///     //--snip--
+    ///     # Ok(())
+    /// }
+    ///
+    /// let command = Command::new("sets the mode.".to_string(), set)
+    ///     .with_completer(|_state, _args, partial| {
+    ///         completion::values(["fast", "slow"], partial)
+    ///     });
+    /// ```
+    pub fn with_completer(mut self, completer: CompleteFn<T>) -> Self {
+        self.completer = Some(completer);
+        self
+    }
+
+    /// Completes the arguments of this command, looking into it if it is a
+    /// namespace. The names of commands within it may be abbreviated if
+    /// `abbreviations` is set.
+    #[cfg(feature = "rustyline")]
+    pub(crate) fn complete(
+        &self,
+        state: &T,
+        args: &[String],
+        partial: &str,
+        abbreviations: bool,
+    ) -> Vec<Completion> {
+        match (&self.command, self.completer) {
+            (CommandType::Namespace(namespace), _) => {
+                namespace.complete(state, &args[1..], partial, abbreviations)
+            }
+            (_, Some(completer)) => completer(state, args, partial),
+            (_, None) => Vec::new(),
         }
     }
 }
 
+// Implemented by hand, as the state doesn't need to be `Clone`.
+impl<T> Clone for Command<T> {
+    fn clone(&self) -> Self {
+        Self {
+            command: self.command.clone(),
+            help: self.help.clone(),
+            usage: self.usage.clone(),
+            completer: self.completer,
+            guard: self.guard,
+            hide_when_unavailable: self.hide_when_unavailable,
+            confirmation: self.confirmation.clone(),
+            long_help: self.long_help.clone(),
+            examples: self.examples.clone(),
+            category: self.category.clone(),
+            priority: self.priority,
+        }
+    }
+}
+
+/// The commands of a shell, by name.
+///
+/// Names can be borrowed (`"greet".into()`) or owned, so that commands can
+/// be loaded whilst the program is running.
+pub type Commands<'a, T> = IndexMap<Cow<'a, str>, Command<T>>;
+
 /// Stores a function for a [`Command`](Command).
 ///
 /// It requires the function returns a `Result<(), Box<dyn Error>>`.
 pub type CommandFn<T> = fn(&mut T, Vec<String>) -> Result<(), Box<dyn Error>>;
 
+/// Stores a function for a [`Command`](Command) which is given the
+/// [`Context`] rather than just the state.
+///
+/// It requires the function returns a `Result<(), Box<dyn Error>>`.
+pub type ContextCommandFn<T> =
+    fn(&mut Context<'_, '_, T>, Vec<String>) -> Result<(), Box<dyn Error>>;
+
+/// Completes the arguments of a [`Command`](Command).
+///
+/// It is given the state, the arguments before the one being completed
+/// (starting with the command name) and the partial argument, and returns
+/// the candidates.
+pub type CompleteFn<T> = fn(&T, &[String], &str) -> Vec<Completion>;
+
+/// Decides whether a [`Command`](Command) is available given the state.
+///
+/// It returns the reason if the command is unavailable.
+pub type GuardFn<T> = fn(&T) -> Result<(), String>;
+
 /// Stores an asynchronous function for a [`Command`](Command).
 ///
 /// It requires the function returns a `Result<(), Box<dyn Error>>`.
@@ -125,10 +463,25 @@ pub type AsyncCommandFn<T> = fn(
 >;
 
 /// Command type specifies what type of command this is, namely wether it
-/// is async or not.
-#[derive(Clone)]
+/// is async or not, whether it is given the context, or whether it is a
+/// namespace of other commands.
 pub enum CommandType<T> {
     Sync(CommandFn<T>),
     #[cfg(feature = "async")]
     Async(AsyncCommandFn<T>),
+    Context(ContextCommandFn<T>),
+    Namespace(Namespace<T>),
+}
+
+// Implemented by hand, as the state doesn't need to be `Clone`.
+impl<T> Clone for CommandType<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Sync(command) => Self::Sync(*command),
+            #[cfg(feature = "async")]
+            Self::Async(command) => Self::Async(*command),
+            Self::Context(command) => Self::Context(*command),
+            Self::Namespace(namespace) => Self::Namespace(namespace.clone()),
+        }
+    }
 }
@@ -0,0 +1,87 @@
+//! # Completion
+//!
+//! Commands can complete their own arguments by giving a
+//! [`CompleteFn`](crate::command::CompleteFn) to
+//! [`Command::with_completer`](crate::Command::with_completer). This module
+//! contains the candidates these return, along with some ready made
+//! completers.
+
+use std::fs;
+use std::path::Path;
+
+/// A candidate for completing an argument.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Completion {
+    /// The value which is put into the line.
+    pub value: String,
+    /// An optional description, shown alongside the value when listing the
+    /// candidates.
+    pub description: Option<String>,
+}
+
+impl Completion {
+    /// Creates a new candidate without a description.
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            description: None,
+        }
+    }
+
+    /// Creates a new candidate with a description.
+    pub fn with_description(
+        value: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            value: value.into(),
+            description: Some(description.into()),
+        }
+    }
+}
+
+/// Completes from a fixed list of values, such as the variants of an enum.
+pub fn values<'v>(
+    values: impl IntoIterator<Item = &'v str>,
+    partial: &str,
+) -> Vec<Completion> {
+    values
+        .into_iter()
+        .filter(|value| value.starts_with(partial))
+        .map(Completion::new)
+        .collect()
+}
+
+/// Completes file paths. Directories are completed with a trailing `/`.
+pub fn paths(partial: &str) -> Vec<Completion> {
+    // Split it into the directory and the start of the file name
+    let (dir, file) = match partial.rfind('/') {
+        Some(i) => partial.split_at(i + 1),
+        None => ("", partial),
+    };
+
+    let entries = match fs::read_dir(if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut completions: Vec<Completion> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(file)
+                || (name.starts_with('.') && file.is_empty())
+            {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(Completion::new(format!("{}{}{}", dir, name, slash)))
+        })
+        .collect();
+    completions.sort_by(|a, b| a.value.cmp(&b.value));
+    completions
+}
@@ -0,0 +1,390 @@
+//! # Context
+//!
+//! A context is what a [`Handler`](crate::Handler) is given by the shell or
+//! app it is running in. It holds the commands and state, along with
+//! everything else which changes how commands are run.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::builtin::Action;
+use crate::builtin::Builtins;
+#[cfg(feature = "async")]
+use crate::command::AsyncCommandFn;
+use crate::command::{CommandFn, CommandType, Commands, ContextCommandFn};
+#[cfg(feature = "async")]
+use crate::executor::Executor;
+use crate::handler::{self, Failure};
+use crate::input_handler::{Input, InputResult};
+use crate::mode::Transition;
+use crate::*;
+
+/// Everything a [`Handler`](crate::Handler) needs from a shell or app to
+/// run a line.
+pub struct Context<'s, 'a, T> {
+    /// The built ins, by name.
+    pub builtins: &'s Builtins<'a>,
+    /// The commands which can be run.
+    ///
+    /// These may be changed by commands, see
+    /// [`new_with_context`](Command::new_with_context).
+    pub commands: &'s mut Commands<'a, T>,
+    /// The state of the shell.
+    pub state: &'s mut T,
+    /// The description of the shell, for the help.
+    pub description: &'s str,
+    /// The middleware to run around each command.
+    pub middleware: &'s [Arc<dyn Middleware<T>>],
+    /// The line being run, as it was entered, which errors can point into.
+    /// Apps are given their arguments rather than a line, so rebuild it
+    /// from them.
+    pub line: &'s str,
+    /// Where the output of built ins goes. Commands which are given the
+    /// context can write to it too, so that their output can be captured.
+    pub output: &'s mut (dyn Write + Send),
+    /// Where errors go, as with [`output`](Self::output).
+    pub error: &'s mut (dyn Write + Send),
+    /// Where to read answers to questions from.
+    pub input_handler: Input<'s>,
+    /// Whether the shell is being run non-interactively, in which case no
+    /// questions should be asked.
+    pub batch: bool,
+    /// Whether command names can be abbreviated, including those within
+    /// namespaces. See [`Shell::abbreviations`](crate::Shell::abbreviations).
+    pub abbreviations: bool,
+    /// What is told about anything which goes wrong.
+    pub reporter: &'s dyn Reporter,
+    /// The colours to use for the output, which are plain if colours are
+    /// turned off.
+    pub theme: &'s Theme,
+    /// The colours to use for errors. These are resolved apart from the
+    /// output's, as errors may go to a terminal when the output doesn't.
+    pub error_theme: &'s Theme,
+    /// The width of the output in columns, which help is wrapped to.
+    pub width: usize,
+    /// Runs asynchronous commands in synchronous shells.
+    #[cfg(feature = "async")]
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    pub executor: &'s dyn Executor,
+    /// Changes to the modes asked for whilst running, which the shell makes
+    /// once the line has been handled.
+    pub(crate) transitions: Vec<Transition<'a, T>>,
+    /// Whether a line which was dispatched failed, for
+    /// [`Outcome::success`](crate::shell::Outcome::success).
+    pub(crate) failed: bool,
+}
+
+impl<'a, T> Context<'_, 'a, T> {
+    /// Adds a command, replacing any with the same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        command: Command<T>,
+    ) -> Option<Command<T>> {
+        self.commands.insert(name.into(), command)
+    }
+
+    /// Removes a command, returning it if it was there. The order of the
+    /// other commands is kept.
+    pub fn unregister(&mut self, name: &str) -> Option<Command<T>> {
+        self.commands.shift_remove(name)
+    }
+
+    /// Enters a mode once the current line has been run. See the
+    /// [`mode`](crate::mode) module for more.
+    ///
+    /// Apps have no modes, so this does nothing in them.
+    pub fn push_mode(&mut self, mode: Mode<'a, T>) {
+        self.transitions.push(Transition::Push(mode));
+    }
+
+    /// Goes back to the mode before the current one once the current line
+    /// has been run. This does nothing if no mode has been entered.
+    pub fn pop_mode(&mut self) {
+        self.transitions.push(Transition::Pop);
+    }
+}
+
+impl<T> Context<'_, '_, T> {
+    /// Runs a line which doesn't start with a built in, as the default
+    /// handlers do. This finds the command, expanding its name if it is
+    /// abbreviated, and checks that it can be run. It asks for
+    /// confirmation if the command needs it, then runs it with the
+    /// middleware around it. Anything which goes wrong is reported.
+    ///
+    /// A command which isn't found is suggested one within
+    /// `suggestion_threshold` edits of it, as with
+    /// [`DefaultHandler::suggestion_threshold`](crate::handler::DefaultHandler::suggestion_threshold).
+    ///
+    /// Returns the error of a command which failed or couldn't be run, once
+    /// it has been reported. An empty line does nothing.
+    pub fn dispatch(
+        &mut self,
+        line: Vec<String>,
+        suggestion_threshold: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        if line.is_empty() {
+            return Ok(());
+        }
+        handler::run("", line, self, suggestion_threshold).map_err(|failure| {
+            self.failed = true;
+            handler::report(self, failure)
+        })
+    }
+
+    /// Runs a line which doesn't start with a built in asynchronously, see
+    /// [`dispatch`](Self::dispatch).
+    #[cfg(feature = "async")]
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    pub async fn dispatch_async(
+        &mut self,
+        line: Vec<String>,
+        suggestion_threshold: usize,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        T: Send,
+    {
+        if line.is_empty() {
+            return Ok(());
+        }
+        match handler::run_async("", line, self, suggestion_threshold).await {
+            Ok(()) => Ok(()),
+            Err(failure) => {
+                self.failed = true;
+                Err(handler::report(self, failure))
+            }
+        }
+    }
+
+    /// Runs a built in with its arguments, as the default handlers do.
+    /// [`Action::Help`] prints the help of the shell. Returns whether the
+    /// shell should quit.
+    pub fn run_builtin(&mut self, action: &Action, args: &[String]) -> bool {
+        handler::run_builtin(action, args, self)
+    }
+
+    /// Asks the user a yes or no question, through the input handler.
+    /// Anything but `y` or `yes` is taken as no.
+    pub fn confirm(&mut self, question: &str) -> io::Result<bool> {
+        let prompt = format!("{} [y/N] ", question);
+        Ok(match self.input_handler.read(&prompt)? {
+            InputResult::S(answer) => {
+                matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+            }
+            InputResult::Interrupted | InputResult::EOF => false,
+        })
+    }
+
+    /// Runs a command with the middleware around it.
+    pub fn run(
+        &mut self,
+        command: CommandFn<T>,
+        args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.around(args, |context, args| command(context.state, args))
+    }
+
+    /// Runs a command which is given the context with the middleware
+    /// around it.
+    pub fn run_with_context(
+        &mut self,
+        command: ContextCommandFn<T>,
+        args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.around(args, command)
+    }
+
+    /// Runs a line within a namespace with the middleware around it. The
+    /// first argument is the name of the namespace.
+    ///
+    /// The middleware see the error of a command within the namespace which
+    /// fails, and the failure is given back to be reported as that
+    /// command's.
+    pub(crate) fn run_namespace(
+        &mut self,
+        prefix: &str,
+        args: Vec<String>,
+        threshold: usize,
+    ) -> Result<(), Failure> {
+        let outer = blame(prefix, &args);
+        let mut inner = None;
+        let result = self.around(args, |context, args| {
+            // Taken out whilst running, as it is given the context
+            let (index, name, mut command) =
+                match context.commands.shift_remove_full(&*args[0]) {
+                    Some(entry) => entry,
+                    None => return Ok(()),
+                };
+            let result = match &mut command.command {
+                CommandType::Namespace(namespace) => {
+                    let prefix = format!("{}{} ", prefix, name);
+                    let args = args[1..].to_vec();
+                    namespace.dispatch(&prefix, context, args, threshold)
+                }
+                _ => Ok(()),
+            };
+            context.commands.shift_insert(index, name, command);
+            result.map_err(|failure| split(failure, &mut inner))
+        });
+        result.map_err(|error| join(error, inner.unwrap_or(outer)))
+    }
+
+    /// Runs an asynchronous command with the middleware around it, blocking
+    /// on it with the [`executor`](Self::executor).
+    #[cfg(feature = "async")]
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    pub fn run_blocking(
+        &mut self,
+        command: AsyncCommandFn<T>,
+        args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.around(args, |context, args| {
+            let executor = context.executor;
+            executor.block_on(command(context.state, args))
+        })
+    }
+
+    /// Runs an asynchronous command with the middleware around it.
+    #[cfg(feature = "async")]
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    pub async fn run_async(
+        &mut self,
+        command: AsyncCommandFn<T>,
+        args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let passed = match self.before(&args) {
+            Ok(passed) => passed,
+            Err(result) => return result,
+        };
+
+        let start = Instant::now();
+        let mut result = command(self.state, args.clone()).await;
+        self.after(passed, &args, &mut result, start.elapsed());
+        result
+    }
+
+    /// Runs a line within a namespace asynchronously with the middleware
+    /// around it. The first argument is the name of the namespace.
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_namespace_async(
+        &mut self,
+        prefix: &str,
+        args: Vec<String>,
+        threshold: usize,
+    ) -> Result<(), Failure>
+    where
+        T: Send,
+    {
+        let outer = blame(prefix, &args);
+        let passed = match self.before(&args) {
+            Ok(passed) => passed,
+            Err(result) => return result.map_err(|error| join(error, outer)),
+        };
+
+        let start = Instant::now();
+
+        // Taken out whilst running, as it is given the context
+        let (index, name, mut command) =
+            match self.commands.shift_remove_full(&*args[0]) {
+                Some(entry) => entry,
+                None => return Ok(()),
+            };
+        let mut inner = None;
+        let mut result = match &mut command.command {
+            CommandType::Namespace(namespace) => {
+                let prefix = format!("{}{} ", prefix, name);
+                let args = args[1..].to_vec();
+                match namespace
+                    .dispatch_async(&prefix, self, args, threshold)
+                    .await
+                {
+                    Ok(()) => Ok(()),
+                    Err(failure) => Err(split(failure, &mut inner)),
+                }
+            }
+            _ => Ok(()),
+        };
+        self.commands.shift_insert(index, name, command);
+
+        self.after(passed, &args, &mut result, start.elapsed());
+        result.map_err(|error| join(error, inner.unwrap_or(outer)))
+    }
+
+    /// Runs `f` with the middleware around it.
+    fn around(
+        &mut self,
+        args: Vec<String>,
+        f: impl FnOnce(&mut Self, Vec<String>) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let passed = match self.before(&args) {
+            Ok(passed) => passed,
+            Err(result) => return result,
+        };
+
+        let start = Instant::now();
+        let mut result = f(self, args.clone());
+        self.after(passed, &args, &mut result, start.elapsed());
+        result
+    }
+
+    /// Calls the `before` of each middleware, returning how many let the
+    /// command through. If one stops it, the result is returned as an
+    /// error, after the `after`s of those before it are called.
+    fn before(
+        &mut self,
+        args: &[String],
+    ) -> Result<usize, Result<(), Box<dyn Error>>> {
+        for (i, middleware) in self.middleware.iter().enumerate() {
+            if let ControlFlow::Break(mut result) =
+                middleware.before(self.state, args)
+            {
+                self.after(i, args, &mut result, Duration::ZERO);
+                return Err(result);
+            }
+        }
+        Ok(self.middleware.len())
+    }
+
+    /// Calls the `after` of the first `passed` middleware, in reverse.
+    fn after(
+        &mut self,
+        passed: usize,
+        args: &[String],
+        result: &mut Result<(), Box<dyn Error>>,
+        elapsed: Duration,
+    ) {
+        for middleware in self.middleware[..passed].iter().rev() {
+            middleware.after(self.state, args, result, elapsed);
+        }
+    }
+}
+
+/// Who a failure is reported as: the name of the command, including any
+/// namespaces it is within, its arguments and whether it has been reported.
+type Blame = (String, Vec<String>, bool);
+
+/// Blames a namespace for a failure, where `args` start with its name.
+fn blame(prefix: &str, args: &[String]) -> Blame {
+    (format!("{}{}", prefix, args[0]), args[1..].to_vec(), false)
+}
+
+/// Takes the error of a failure within a namespace for the namespace's
+/// middleware to see, keeping who to blame.
+fn split(failure: Failure, blame: &mut Option<Blame>) -> Box<dyn Error> {
+    *blame = Some((failure.name, failure.args, failure.reported));
+    failure.error
+}
+
+/// Puts a failure back together once the middleware have seen its error.
+fn join(error: Box<dyn Error>, (name, args, reported): Blame) -> Failure {
+    Failure {
+        name,
+        args,
+        error,
+        reported,
+    }
+}
@@ -0,0 +1,85 @@
+//! # Diagnostics
+//!
+//! A diagnostic is an error which knows more about what went wrong than
+//! its message. It can point to the argument which caused it, give some
+//! help and suggest what was meant. Commands return them like any other
+//! error, and the default [`Reporter`](crate::Reporter) shows them as:
+//!
+//! ```text
+//! error: invalid port
+//!   | connect localhost 99999
+//!   |                   ^^^^^
+//! help: ports must be 1-65535
+//! ```
+//!
+//! ```rust
+//! use shellfish::diagnostic::Diagnostic;
+//! use std::error::Error;
+//!
+//! fn connect(_state: &mut (), args: Vec<String>) -> Result<(), Box<dyn Error>> {
+//!     let port: u16 = args[2].parse().map_err(|_| {
+//!         Diagnostic::new("invalid port".to_string())
+//!             .at(2)
+//!             .with_help("ports must be 1-65535".to_string())
+//!     })?;
+//!     println!("Connecting to {}:{}", args[1], port);
+//!     Ok(())
+//! }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+/// An error with optional help, a suggestion, and the argument which
+/// caused it. See the [module level documentation](self).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// What went wrong.
+    pub message: String,
+    /// How to put it right.
+    pub help: Option<String>,
+    /// What the user may have meant instead.
+    pub suggestion: Option<String>,
+    /// The index of the argument which caused the error, within the
+    /// arguments given to the command (so `0` is the command's name).
+    pub arg: Option<usize>,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic with just a message.
+    pub fn new(message: String) -> Self {
+        Self {
+            message,
+            help: None,
+            suggestion: None,
+            arg: None,
+        }
+    }
+
+    /// Sets the help of this diagnostic.
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    /// Sets what the user may have meant.
+    pub fn with_suggestion(mut self, suggestion: String) -> Self {
+        self.suggestion = Some(suggestion);
+        self
+    }
+
+    /// Points this diagnostic at an argument, where `0` is the command's
+    /// name.
+    pub fn at(mut self, arg: usize) -> Self {
+        self.arg = Some(arg);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for Diagnostic {}
@@ -0,0 +1,201 @@
+//! # Reference documentation
+//!
+//! Generates man pages and Markdown from the commands of a shell, so that
+//! reference documentation doesn't have to be written by hand. Every
+//! command is documented with its help, usage, long help and examples,
+//! including those within namespaces, grouped by category. Built ins are
+//! left out.
+//!
+//! ```rust
+//! use shellfish::docs::Reference;
+//! use shellfish::{Command, Shell};
+//!
+//! let mut shell = Shell::new((), "[Shell]-$");
+//! shell.description = "Manages the widgets.".to_string();
+//! shell.commands.insert(
+//!     "count".into(),
+//!     Command::new("counts the widgets.".to_string(), |_, _| Ok(()))
+//!         .with_usage("[colour]".to_string()),
+//! );
+//!
+//! let reference = Reference::new(
+//!     "widgets".to_string(),
+//!     shell.description.clone(),
+//!     &shell.commands,
+//! );
+//! assert!(reference.man().starts_with(".TH WIDGETS 1"));
+//! assert!(reference.markdown().contains("### `count [colour]`"));
+//! ```
+//!
+//! [`Reference::write`] writes both files at once, for example from a
+//! build script. Apps also take a hidden `--generate-docs [dir]` flag,
+//! which does the same for their commands.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::command::Commands;
+use crate::help::{self, HelpEntry};
+
+/// The documentation of a shell's commands. See the
+/// [module level documentation](self).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reference {
+    /// The name of the program, which names the files too.
+    pub name: String,
+    /// What the program does. Its first line is the summary in the man
+    /// page's `NAME` section.
+    pub description: String,
+    /// The commands, including those within namespaces.
+    pub entries: Vec<HelpEntry>,
+}
+
+impl Reference {
+    /// Creates the reference for some commands.
+    pub fn new<T>(
+        name: String,
+        description: String,
+        commands: &Commands<'_, T>,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            entries: help::reference_entries(commands),
+        }
+    }
+
+    /// Renders the reference as a roff man page, in section 1.
+    pub fn man(&self) -> String {
+        let mut man = String::new();
+        let _ = writeln!(man, ".TH {} 1", roff(&self.name.to_uppercase()));
+
+        // The name, and what it does in a line
+        let _ = writeln!(man, ".SH NAME");
+        match self.description.lines().next() {
+            Some(summary) if !summary.trim().is_empty() => {
+                let name = roff(&self.name);
+                let _ = writeln!(man, "{} \\- {}", name, roff(summary.trim()));
+            }
+            _ => {
+                let _ = writeln!(man, "{}", roff(&self.name));
+            }
+        }
+        let _ = writeln!(man, ".SH SYNOPSIS");
+        let _ =
+            writeln!(man, "\\fB{}\\fR [\\fISUBCOMMAND\\fR]", roff(&self.name));
+        if !self.description.trim().is_empty() {
+            let _ = writeln!(man, ".SH DESCRIPTION");
+            for (i, paragraph) in paragraphs(&self.description).enumerate() {
+                if i > 0 {
+                    let _ = writeln!(man, ".PP");
+                }
+                let _ = writeln!(man, "{}", roff(paragraph));
+            }
+        }
+
+        // Each command, tagged with its name and usage
+        for (category, entries) in help::categorise(self.entries.clone()) {
+            let heading = category.as_deref().unwrap_or("Commands");
+            let _ = writeln!(man, ".SH {}", roff(&heading.to_uppercase()));
+            for entry in entries {
+                let _ = writeln!(man, ".TP");
+                let _ = match &entry.usage {
+                    Some(usage) => writeln!(
+                        man,
+                        "\\fB{}\\fR {}",
+                        roff(&entry.name),
+                        roff(usage)
+                    ),
+                    None => writeln!(man, "\\fB{}\\fR", roff(&entry.name)),
+                };
+                let _ = writeln!(man, "{}", roff(&entry.help));
+                for paragraph in
+                    entry.long_help.as_deref().into_iter().flat_map(paragraphs)
+                {
+                    let _ = writeln!(man, ".IP\n{}", roff(paragraph));
+                }
+                if !entry.examples.is_empty() {
+                    let _ = writeln!(man, ".IP\nExamples:\n.RS\n.nf");
+                    for example in &entry.examples {
+                        let _ = writeln!(man, "{}", roff(example));
+                    }
+                    let _ = writeln!(man, ".fi\n.RE");
+                }
+            }
+        }
+        man
+    }
+
+    /// Renders the reference as Markdown.
+    pub fn markdown(&self) -> String {
+        let mut markdown = String::new();
+        let _ = writeln!(markdown, "# {}", self.name);
+        for paragraph in paragraphs(&self.description) {
+            let _ = writeln!(markdown, "\n{}", paragraph);
+        }
+
+        // Each command, headed by its name and usage
+        for (category, entries) in help::categorise(self.entries.clone()) {
+            let heading = category.as_deref().unwrap_or("Commands");
+            let _ = writeln!(markdown, "\n## {}", heading);
+            for entry in entries {
+                let _ = match &entry.usage {
+                    Some(usage) => {
+                        writeln!(markdown, "\n### `{} {}`", entry.name, usage)
+                    }
+                    None => writeln!(markdown, "\n### `{}`", entry.name),
+                };
+                let _ = writeln!(markdown, "\n{}", entry.help);
+                for paragraph in
+                    entry.long_help.as_deref().into_iter().flat_map(paragraphs)
+                {
+                    let _ = writeln!(markdown, "\n{}", paragraph);
+                }
+                if !entry.examples.is_empty() {
+                    let _ = writeln!(markdown, "\nExamples:\n\n```text");
+                    for example in &entry.examples {
+                        let _ = writeln!(markdown, "{}", example);
+                    }
+                    let _ = writeln!(markdown, "```");
+                }
+            }
+        }
+        markdown
+    }
+
+    /// Writes the man page and Markdown into `dir` as `<name>.1` and
+    /// `<name>.md`, creating it if needed, and returns their paths.
+    pub fn write(&self, dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let man = dir.join(format!("{}.1", self.name));
+        fs::write(&man, self.man())?;
+        let markdown = dir.join(format!("{}.md", self.name));
+        fs::write(&markdown, self.markdown())?;
+        Ok(vec![man, markdown])
+    }
+}
+
+/// Splits text into its paragraphs, which are separated by blank lines.
+fn paragraphs(text: &str) -> impl Iterator<Item = &str> {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+}
+
+/// Escapes text for roff, so that it is shown as it is written.
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+
+    // Lines starting with these would be read as requests
+    escaped
+        .lines()
+        .map(|line| match line.starts_with(['.', '\'']) {
+            true => format!("\\&{}", line),
+            false => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,229 @@
+//! # Executors
+//!
+//! An executor lets a synchronous [`Shell`](crate::Shell) or
+//! [`App`](crate::App) run asynchronous commands, by blocking on them until
+//! they finish. This means command packs can mix both kinds of command.
+//!
+//! The default is [`BlockOn`], a small executor which runs the command on
+//! the shell's own thread. It can't drive commands which need a runtime,
+//! such as those using tokio's IO, so with the `tokio` feature a runtime
+//! [`Handle`](tokio::runtime::Handle) can be used instead. With the
+//! `async-std` feature there is [`AsyncStd`].
+//!
+//! ```rust,ignore
+//! use std::sync::Arc;
+//!
+//! let runtime = tokio::runtime::Runtime::new()?;
+//! shell.executor = Arc::new(runtime.handle().clone());
+//! ```
+
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// The future of an asynchronous command.
+pub type CommandFuture<'f> =
+    Pin<Box<dyn Future<Output = Result<(), Box<dyn Error>>> + Send + 'f>>;
+
+/// Runs asynchronous commands in synchronous shells. See the
+/// [module level documentation](self).
+pub trait Executor: Send + Sync {
+    /// Runs a command's future until it is finished.
+    fn block_on(&self, future: CommandFuture<'_>)
+        -> Result<(), Box<dyn Error>>;
+}
+
+/// Shellfish's built in executor, which polls the future on the current
+/// thread, parking it whilst the future waits.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct BlockOn;
+
+/// Wakes a parked thread.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+impl Executor for BlockOn {
+    fn block_on(
+        &self,
+        mut future: CommandFuture<'_>,
+    ) -> Result<(), Box<dyn Error>> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut context = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(result) => return result,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[cfg_attr(nightly, doc(cfg(feature = "tokio")))]
+impl Executor for tokio::runtime::Handle {
+    fn block_on(
+        &self,
+        future: CommandFuture<'_>,
+    ) -> Result<(), Box<dyn Error>> {
+        tokio::runtime::Handle::block_on(self, future)
+    }
+}
+
+/// Runs commands with async-std's `block_on`.
+#[cfg(feature = "async-std")]
+#[cfg_attr(nightly, doc(cfg(feature = "async-std")))]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct AsyncStd;
+
+#[cfg(feature = "async-std")]
+impl Executor for AsyncStd {
+    fn block_on(
+        &self,
+        future: CommandFuture<'_>,
+    ) -> Result<(), Box<dyn Error>> {
+        async_std::task::block_on(future)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::future::Future;
+    use std::ops::ControlFlow;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::{async_fn, testing, Command, Middleware};
+
+    /// Waits once, being woken from another thread.
+    struct Yield(bool);
+
+    impl Future for Yield {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            let waker = cx.waker().clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                waker.wake();
+            });
+            Poll::Pending
+        }
+    }
+
+    async fn wait(
+        log: &mut Vec<String>,
+        args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        log.push("waiting".to_string());
+        Yield(false).await;
+        log.push(format!("woken with {}", args[1..].join(" ")));
+        Ok(())
+    }
+
+    async fn fail(
+        _log: &mut Vec<String>,
+        _args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        Yield(false).await;
+        Err("gave up".into())
+    }
+
+    /// Logs what it sees of each command.
+    struct Log;
+
+    impl Middleware<Vec<String>> for Log {
+        fn before(
+            &self,
+            log: &mut Vec<String>,
+            args: &[String],
+        ) -> ControlFlow<Result<(), Box<dyn Error>>> {
+            log.push(format!("before {}", args[0]));
+            ControlFlow::Continue(())
+        }
+
+        fn after(
+            &self,
+            log: &mut Vec<String>,
+            args: &[String],
+            result: &mut Result<(), Box<dyn Error>>,
+            _elapsed: Duration,
+        ) {
+            let outcome = match result {
+                Ok(()) => "ok".to_string(),
+                Err(e) => e.to_string(),
+            };
+            log.push(format!("after {} {}", args[0], outcome));
+        }
+    }
+
+    fn shell() -> crate::Shell<
+        'static,
+        Vec<String>,
+        &'static str,
+        crate::handler::DefaultHandler,
+        testing::Script,
+    > {
+        let mut shell = testing::shell(Vec::new());
+        shell.commands.insert(
+            "wait".into(),
+            Command::new_async(
+                "waits once.".to_string(),
+                async_fn!(Vec<String>, wait),
+            ),
+        );
+        shell.commands.insert(
+            "fail".into(),
+            Command::new_async(
+                "waits once, then fails.".to_string(),
+                async_fn!(Vec<String>, fail),
+            ),
+        );
+        shell
+    }
+
+    #[test]
+    fn blocks_on_commands_which_wait() {
+        let mut shell = shell();
+        shell.execute("wait a while").assert_success();
+        assert_eq!(shell.state, ["waiting", "woken with a while"]);
+        shell
+            .execute("fail")
+            .assert_failure()
+            .assert_stderr_contains("gave up");
+    }
+
+    #[test]
+    fn middleware_wraps_blocked_commands() {
+        let mut shell = shell();
+        shell.middleware.push(Arc::new(Log));
+        shell.execute("wait").assert_success();
+        shell.execute("fail").assert_failure();
+        assert_eq!(
+            shell.state,
+            [
+                "before wait",
+                "waiting",
+                "woken with ",
+                "after wait ok",
+                "before fail",
+                "after fail gave up",
+            ]
+        );
+    }
+}
@@ -1,13 +1,13 @@
 //use std::collections::HashMap;
-use indexmap::IndexMap;
 use std::env;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 
-use yansi::Paint;
-
-use crate::command::CommandType;
-use crate::Command;
-use crate::Handler;
+use super::{print_narrowed, DEFAULT_SUGGESTION_THRESHOLD};
+use crate::builtin::Action;
+use crate::docs::Reference;
+use crate::help::{builtin_entries, help_entries, Help};
+use crate::{Context, Handler};
 
 pub trait CommandLineHandler {
     /// This is where the cache is stored:
@@ -26,9 +26,23 @@ pub trait CommandLineHandler {
 /// The main differences are:
 ///  * It expects the binary name to be first
 ///  * Aswell as `help` one can use `--help`
-#[derive(Default, Clone, Eq, PartialEq)]
+///
+/// When a command can't be found, the closest command name is suggested.
+#[derive(Clone, Eq, PartialEq)]
 pub struct DefaultCommandLineHandler {
     pub proj_name: Option<String>,
+    /// How many edits a mistyped command may be from a real one for the
+    /// real one to be suggested. `0` turns suggestions off.
+    pub suggestion_threshold: usize,
+}
+
+impl Default for DefaultCommandLineHandler {
+    fn default() -> Self {
+        Self {
+            proj_name: None,
+            suggestion_threshold: DEFAULT_SUGGESTION_THRESHOLD,
+        }
+    }
 }
 
 impl CommandLineHandler for DefaultCommandLineHandler {
@@ -57,94 +71,109 @@ impl<T> Handler<T> for DefaultCommandLineHandler {
     fn handle(
         &self,
         line: Vec<String>,
-        commands: &IndexMap<&str, Command<T>>,
-        state: &mut T,
-        description: &str,
+        context: &mut Context<'_, '_, T>,
     ) -> bool {
         if let Some(command) = line.get(1) {
-            match command.as_str() {
-                "quit" | "exit" | "--quit" | "--exit" => return true,
-                "help" | "--help" => {
-                    // Print the binary name
-                    println!("{}", line[0]);
-
-                    // Description
-                    println!("{}", description);
-
-                    // Usage section
-                    println!("USAGE:");
-                    println!(
-                        "    {} [SUBCOMMAND]",
-                        self.proj_name.as_ref().unwrap_or(&line[0])
-                    );
-                    println!();
-
-                    // Subcommand section
-                    println!("Where [SUBCOMMAND] is one of:");
-
-                    // Create a list of commands
-                    let mut cmd_help = IndexMap::new();
-                    let mut cmd_len = 4;
-
-                    // Add the built ins
-                    cmd_help.insert("help", "displays help information.");
-                    cmd_help.insert(
-                        "quit",
-                        "deletes all temporary state information.",
-                    );
-                    cmd_help.insert(
-                        "exit",
-                        "deletes all temporary state information.",
-                    );
-
-                    // Add the user defined
-                    for (name, command) in commands {
-                        cmd_help.insert(name, &command.help);
-                        cmd_len = cmd_len.max(name.len());
-                    }
+            // A hidden flag, for writing reference documentation
+            if command == GENERATE_DOCS {
+                let name = self.proj_name.as_ref().unwrap_or(&line[0]);
+                generate_docs(name, line.get(2), context);
+                return false;
+            }
 
-                    // Go through the built in subcommands
-                    for (name, command) in cmd_help {
-                        println!(
-                            "    {:<width$}{}",
-                            name,
-                            command,
-                            width = cmd_len + 5
-                        );
-                    }
+            // Built ins can also be given as flags, such as `--help`
+            let name = command.strip_prefix("--").unwrap_or(command);
+            let builtin = context.builtins.get(name);
+            match builtin.map(|builtin| builtin.action.clone()) {
+                Some(Action::Help) => {
+                    let name = self.proj_name.as_ref().unwrap_or(&line[0]);
+                    let _ = print_help(&line[0], name, &line[2..], context);
                 }
-                _ => {
-                    let command = commands.get(command as &str);
-                    let line =
-                        line[1..].iter().map(|x| x.to_string()).collect();
-
-                    // Checks if we got it
-                    match command {
-                        Some(command) => {
-                            if let Err(e) = match command.command {
-                                CommandType::Sync(c) => c(state, line),
-                                #[cfg(feature = "async")]
-                                CommandType::Async(_) => Ok(eprintln!("{}", Paint::red("Async commands cannot be run in sync apps.")))
-                            } {
-                                eprintln!("{}", Paint::red(format!("Command exited unsuccessfully:\n{}\n({:?})", &e, &e)))
-                            }
-                        }
-                        None => {
-                            eprintln!(
-                                "{}",
-                                Paint::red(format!(
-                                    "Command not found: {}",
-                                    line[0]
-                                )),
-                            )
-                        }
+                Some(action) => {
+                    if context.run_builtin(&action, &line[2..]) {
+                        return true;
                     }
                 }
+                None => {
+                    let line = line[1..].to_vec();
+                    let _ = context.dispatch(line, self.suggestion_threshold);
+                }
             }
 
             // Padding
-            println!();
+            let _ = writeln!(context.output);
         }
         false
     }
 }
+
+/// The hidden flag which writes the reference documentation of an app.
+pub(crate) const GENERATE_DOCS: &str = "--generate-docs";
+
+/// Writes the reference documentation of the commands into `dir`, or the
+/// current directory, for the `--generate-docs` flag. The files are named
+/// after the binary.
+pub(crate) fn generate_docs<T>(
+    bin: &str,
+    dir: Option<&String>,
+    context: &mut Context<'_, '_, T>,
+) {
+    let name = Path::new(bin)
+        .file_name()
+        .map_or(bin.into(), |name| name.to_string_lossy());
+    let reference = Reference::new(
+        name.to_string(),
+        context.description.to_string(),
+        context.commands,
+    );
+    match reference.write(dir.map_or(".", String::as_str)) {
+        Ok(paths) => {
+            for path in paths {
+                let _ = writeln!(context.output, "Wrote {}", path.display());
+            }
+        }
+        Err(e) => {
+            let message = format!("Couldn't write the documentation: {}", e);
+            let message = context.error_theme.error.paint(message);
+            let _ = writeln!(context.error, "{}", message);
+        }
+    }
+}
+
+/// Prints the help of the command line handlers, where `bin` is the name the
+/// binary was run as and `name` is the name to show in the usage. The
+/// arguments can narrow it down, see [`print_narrowed`].
+pub(crate) fn print_help<T>(
+    bin: &str,
+    name: &str,
+    args: &[String],
+    context: &mut Context<'_, '_, T>,
+) -> io::Result<()> {
+    let mut entries = builtin_entries(context.builtins);
+    entries.extend(help_entries(context.commands, context.state));
+    if !args.is_empty() {
+        return print_narrowed(args, entries, context);
+    }
+
+    let out = &mut *context.output;
+    let theme = context.theme;
+
+    // Print the binary name
+    writeln!(out, "{}", bin)?;
+
+    // Description
+    writeln!(out, "{}", context.description)?;
+
+    // Usage section
+    writeln!(out, "{}", theme.heading.paint("USAGE:"))?;
+    writeln!(out, "    {} [SUBCOMMAND]", name)?;
+    writeln!(out)?;
+
+    // Subcommand section
+    Help::new(context.width)
+        .with_entries(
+            Some("Where [SUBCOMMAND] is one of:".to_string()),
+            entries,
+        )
+        .render(out, theme)
+}
@@ -1,14 +1,13 @@
 //use std::collections::HashMap;
-use indexmap::IndexMap;
 use std::env;
 use std::path::PathBuf;
 
 use async_trait::async_trait;
-use yansi::Paint;
 
-use super::{AsyncHandler, CommandLineHandler};
-use crate::command::CommandType;
-use crate::Command;
+use super::app::{generate_docs, print_help, GENERATE_DOCS};
+use super::{AsyncHandler, CommandLineHandler, DEFAULT_SUGGESTION_THRESHOLD};
+use crate::builtin::Action;
+use crate::Context;
 
 /// Shellfish's CLI handler. This is helpful for when you want to parse
 /// input from the command line, rather than in an interactive case.
@@ -16,9 +15,23 @@ use crate::Command;
 /// The main differences are:
 ///  * It expects the binary name to be first
 ///  * Aswell as `help` one can use `--help`
-#[derive(Default, Clone, Eq, PartialEq)]
+///
+/// When a command can't be found, the closest command name is suggested.
+#[derive(Clone, Eq, PartialEq)]
 pub struct DefaultAsyncCLIHandler {
     pub proj_name: Option<String>,
+    /// How many edits a mistyped command may be from a real one for the
+    /// real one to be suggested. `0` turns suggestions off.
+    pub suggestion_threshold: usize,
+}
+
+impl Default for DefaultAsyncCLIHandler {
+    fn default() -> Self {
+        Self {
+            proj_name: None,
+            suggestion_threshold: DEFAULT_SUGGESTION_THRESHOLD,
+        }
+    }
 }
 
 impl CommandLineHandler for DefaultAsyncCLIHandler {
@@ -48,93 +61,38 @@ impl<T: Send> AsyncHandler<T> for DefaultAsyncCLIHandler {
     async fn handle_async(
         &self,
         line: Vec<String>,
-        commands: &IndexMap<&str, Command<T>>,
-        state: &mut T,
-        description: &str,
+        context: &mut Context<'_, '_, T>,
     ) -> bool {
         if let Some(command) = line.get(1) {
-            match command.as_str() {
-                "quit" | "exit" | "--quit" | "--exit" => return true,
-                "help" | "--help" => {
-                    // Print the binary name
-                    println!("{}", line[0]);
-
-                    // Description
-                    println!("{}", description);
-
-                    // Usage section
-                    println!("USAGE:");
-                    println!(
-                        "    {} [SUBCOMMAND]",
-                        self.proj_name.as_ref().unwrap_or(&line[0])
-                    );
-                    println!();
-
-                    // Subcommand section
-                    println!("Where [SUBCOMMAND] is one of:");
-
-                    // Create a list of commands
-                    let mut cmd_help = IndexMap::new();
-                    let mut cmd_len = 4;
-
-                    // Add the built ins
-                    cmd_help.insert("help", "displays help information.");
-                    cmd_help.insert(
-                        "quit",
-                        "deletes all temporary state information.",
-                    );
-                    cmd_help.insert(
-                        "exit",
-                        "deletes all temporary state information.",
-                    );
-
-                    // Add the user defined
-                    for (name, command) in commands {
-                        cmd_help.insert(name, &command.help);
-                        cmd_len = cmd_len.max(name.len());
-                    }
+            // A hidden flag, for writing reference documentation
+            if command == GENERATE_DOCS {
+                let name = self.proj_name.as_ref().unwrap_or(&line[0]);
+                generate_docs(name, line.get(2), context);
+                return false;
+            }
 
-                    // Go through the built in subcommands
-                    for (name, command) in cmd_help {
-                        println!(
-                            "    {:<width$}{}",
-                            name,
-                            command,
-                            width = cmd_len + 5
-                        );
-                    }
+            // Built ins can also be given as flags, such as `--help`
+            let name = command.strip_prefix("--").unwrap_or(command);
+            let builtin = context.builtins.get(name);
+            match builtin.map(|builtin| builtin.action.clone()) {
+                Some(Action::Help) => {
+                    let name = self.proj_name.as_ref().unwrap_or(&line[0]);
+                    let _ = print_help(&line[0], name, &line[2..], context);
                 }
-                _ => {
-                    let command = commands.get(command as &str);
-                    let line =
-                        line[1..].iter().map(|x| x.to_string()).collect();
-
-                    // Checks if we got it
-                    match command {
-                        Some(command) => {
-                            if let Err(e) = match command.command {
-                                CommandType::Sync(c) => c(state, line),
-                                #[cfg(feature = "async")]
-                                CommandType::Async(a) => a(state, line).await,
-                            } {
-                                eprintln!("{}", Paint::red(format!("Command exited unsuccessfully:\n{}\n({:?})", &e, &e)))
-                            }
-                        }
-                        None => {
-                            eprintln!(
-                                "{}",
-                                Paint::red(format!(
-                                    "Command not found: {}",
-                                    line[0]
-                                )),
-                            )
-                        }
+                Some(action) => {
+                    if context.run_builtin(&action, &line[2..]) {
+                        return true;
                     }
                 }
+                None => {
+                    let line = line[1..].to_vec();
+                    let threshold = self.suggestion_threshold;
+                    let _ = context.dispatch_async(line, threshold).await;
+                }
             }
 
             // Padding
-            println!();
+            let _ = writeln!(context.output);
         }
         false
     }
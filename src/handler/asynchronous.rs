@@ -1,11 +1,7 @@
-//use std::collections::HashMap;
-use indexmap::IndexMap;
-
 use async_trait::async_trait;
-use yansi::Paint;
 
-use crate::command::CommandType;
-use crate::Command;
+use super::DEFAULT_SUGGESTION_THRESHOLD;
+use crate::Context;
 
 /// Async handler lets you run asynchronous commands. It also requires the
 /// shell to be run in asynchronous mode to support it.
@@ -14,71 +10,57 @@ pub trait AsyncHandler<T: Send> {
     async fn handle_async(
         &self,
         args: Vec<String>,
-        commands: &IndexMap<&str, Command<T>>,
-        state: &mut T,
-        description: &str,
+        context: &mut Context<'_, '_, T>,
     ) -> bool;
 }
 
 /// Shellfish's default async handler. This handler is pretty simple, given
-/// the only built in commands are `help`, `quit` and `exit`.
-#[derive(Default, Copy, Clone, Eq, PartialEq)]
-pub struct DefaultAsyncHandler();
+/// the only built in commands are those in
+/// [`Shell::builtins`](crate::Shell::builtins), such as `help`, `quit` and
+/// `exit`.
+///
+/// When a command can't be found, the closest command name is suggested.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct DefaultAsyncHandler {
+    /// How many edits a mistyped command may be from a real one for the
+    /// real one to be suggested. `0` turns suggestions off.
+    pub suggestion_threshold: usize,
+}
+
+impl Default for DefaultAsyncHandler {
+    fn default() -> Self {
+        Self {
+            suggestion_threshold: DEFAULT_SUGGESTION_THRESHOLD,
+        }
+    }
+}
 
 #[async_trait]
 impl<T: Send> AsyncHandler<T> for DefaultAsyncHandler {
     async fn handle_async(
         &self,
         line: Vec<String>,
-        commands: &IndexMap<&str, Command<T>>,
-        state: &mut T,
-        description: &str,
+        context: &mut Context<'_, '_, T>,
     ) -> bool {
-        if let Some(command) = line.get(0) {
+        if let Some(command) = line.first() {
             // Add some padding.
-            println!();
-
-            match command.as_str() {
-                "quit" | "exit" => return true,
-                "help" => {
-                    println!("{}", description);
+            let _ = writeln!(context.output);
 
-                    // Print information about built-in commands
-                    println!("    help - displays help information.");
-                    println!("    quit - quits the shell.");
-                    println!("    exit - exits the shell.");
-                    for (name, command) in commands {
-                        println!("    {} - {}", name, command.help);
+            let builtin = context.builtins.get(&**command);
+            match builtin.map(|builtin| builtin.action.clone()) {
+                Some(action) => {
+                    if context.run_builtin(&action, &line[1..]) {
+                        return true;
                     }
                 }
-                _ => {
-                    // Attempt to find the command
-                    let command = commands.get(&line[0] as &str);
-
-                    // Checks if we got it
-                    match command {
-                        Some(command) => {
-                            if let Err(e) = match command.command {
-                                CommandType::Sync(c) => c(state, line),
-                                #[cfg(feature = "async")]
-                                CommandType::Async(a) => a(state, line).await,
-                            } {
-                                eprintln!("{}", Paint::red(format!("Command exited unsuccessfully:\n{}\n({:?})", &e, &e)))
-                            }
-                        }
-                        None => {
-                            eprintln!(
-                                "{} {}",
-                                Paint::red("Command not found:"),
-                                line[0]
-                            )
-                        }
-                    }
+                None => {
+                    let threshold = self.suggestion_threshold;
+                    let _ = context.dispatch_async(line, threshold).await;
                 }
             }
 
             // Padding
-            println!();
+            let _ = writeln!(context.output);
         }
         false
     }
@@ -1,93 +1,75 @@
-//use std::collections::HashMap;
-use indexmap::IndexMap;
-
-use yansi::Paint;
-
-use crate::command::CommandType;
-use crate::Command;
+use super::DEFAULT_SUGGESTION_THRESHOLD;
+use crate::Context;
 /// A handler lets you change how commands are run. They also let you
 /// change the shell built-ins. A handler takes a Vec<String> as
-/// input, and return a bool. A handler also takes a HashMap<String, Commands>,
-/// so it knows what commands it can run. Likewise, the state is also given.
+/// input, and return a bool. A handler also takes a [`Context`], so it
+/// knows what commands it can run. Likewise, the state is also given.
 ///
 /// The bool sent in return is wether or not this command should quit the
 /// shell. For example, in default shellfish, `true` is only every returned
-/// when the commands `quit` or `exit` are given.
+/// for a built in which quits, such as `quit` or `exit`.
 ///
 /// For nearly every use case
 /// the default handler should be enough. If in doubt, you can create your
 /// own. I would recommend looking at shellfish's source code for an example
+///
+/// A handler can leave the rest to the context once it has handled what it
+/// wants to: [`Context::run_builtin`] runs the built ins and
+/// [`Context::dispatch`] runs commands, including those within namespaces,
+/// as the default handler does.
 pub trait Handler<T> {
     fn handle(
         &self,
         args: Vec<String>,
-        commands: &IndexMap<&str, Command<T>>,
-        state: &mut T,
-        description: &str,
+        context: &mut Context<'_, '_, T>,
     ) -> bool;
 }
 
 /// Shellfish's default handler. This handler is pretty simple, given the
-/// only special options are `help`, `quit` and `exit`.
-#[derive(Default, Copy, Clone, Eq, PartialEq)]
-pub struct DefaultHandler();
+/// only special options are the built ins in
+/// [`Shell::builtins`](crate::Shell::builtins), such as `help`, `quit` and
+/// `exit`.
+///
+/// When a command can't be found, the closest command name is suggested.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct DefaultHandler {
+    /// How many edits a mistyped command may be from a real one for the
+    /// real one to be suggested. `0` turns suggestions off.
+    pub suggestion_threshold: usize,
+}
+
+impl Default for DefaultHandler {
+    fn default() -> Self {
+        Self {
+            suggestion_threshold: DEFAULT_SUGGESTION_THRESHOLD,
+        }
+    }
+}
 
 impl<T> Handler<T> for DefaultHandler {
     fn handle(
         &self,
         line: Vec<String>,
-        commands: &IndexMap<&str, Command<T>>,
-        state: &mut T,
-        description: &str,
+        context: &mut Context<'_, '_, T>,
     ) -> bool {
-        if let Some(command) = line.get(0) {
+        if let Some(command) = line.first() {
             // Add some padding.
-            println!();
+            let _ = writeln!(context.output);
 
-            match command.as_str() {
-                "quit" | "exit" => return true,
-                "help" => {
-                    println!("{}", description);
-
-                    // Print information about built-in commands
-                    println!("    help - displays help information.");
-                    println!("    quit - quits the shell.");
-                    println!("    exit - exits the shell.");
-                    for (name, command) in commands {
-                        println!("    {} - {}", name, command.help);
+            let builtin = context.builtins.get(&**command);
+            match builtin.map(|builtin| builtin.action.clone()) {
+                Some(action) => {
+                    if context.run_builtin(&action, &line[1..]) {
+                        return true;
                     }
                 }
-                _ => {
-                    // Attempt to find the command
-                    let command = commands.get(&*line[0]);
-
-                    // Checks if we got it
-                    match command {
-                        Some(command) => {
-                            if let Err(e) = match command.command {
-                                CommandType::Sync(c) => c(state, line),
-                                #[cfg(feature = "async")]
-                                CommandType::Async(_) => {
-                                    eprintln!("{}", Paint::red("Async commands cannot be run in sync shells."));
-                                    Ok(())
-                                }
-                            } {
-                                eprintln!("{}", Paint::red(format!("Command exited unsuccessfully:\n{}\n({:?})", &e, &e)))
-                            }
-                        }
-                        None => {
-                            eprintln!(
-                                "{} {}",
-                                Paint::red("Command not found:"),
-                                line[0]
-                            )
-                        }
-                    }
+                None => {
+                    let _ = context.dispatch(line, self.suggestion_threshold);
                 }
             }
 
             // Padding
-            println!();
+            let _ = writeln!(context.output);
         }
         false
     }
@@ -7,6 +7,379 @@
 //! in which case [`DefaultAsyncHandler`](asynchronous::DefaultAsyncHandler)
 //! is for you.
 
+use std::error::Error;
+use std::io;
+
+use crate::builtin::Action;
+use crate::command::CommandType;
+use crate::help::{self, builtin_entries, help_entries, Help, HelpEntry};
+use crate::suggest::{self, did_you_mean, Abbreviation};
+use crate::Context;
+
+/// The default for the `suggestion_threshold` of the default handlers.
+pub(crate) const DEFAULT_SUGGESTION_THRESHOLD: usize = 2;
+
+/// Prints the help of the default handlers: the description, then the
+/// built ins and commands. The arguments can narrow it down, see
+/// [`print_narrowed`].
+pub(crate) fn print_help<T>(
+    args: &[String],
+    context: &mut Context<'_, '_, T>,
+) -> io::Result<()> {
+    let mut entries = builtin_entries(context.builtins);
+    entries.extend(help_entries(context.commands, context.state));
+    if !args.is_empty() {
+        return print_narrowed(args, entries, context);
+    }
+
+    writeln!(context.output, "{}", context.description)?;
+    Help::new(context.width)
+        .with_entries(None, entries)
+        .render(context.output, context.theme)
+}
+
+/// Prints some of the help, given the arguments to `help`:
+///
+/// * `help -s <keywords>` (or `--search`) lists the entries which match the
+///   keywords, best first.
+/// * `help <category>` lists the entries within a category, ignoring case.
+pub(crate) fn print_narrowed<T>(
+    args: &[String],
+    mut entries: Vec<HelpEntry>,
+    context: &mut Context<'_, '_, T>,
+) -> io::Result<()> {
+    let search = matches!(args[0].as_str(), "-s" | "--search");
+    let missing = if search {
+        let query = args[1..].join(" ");
+        entries = help::search(entries, &query);
+        match query.trim().is_empty() {
+            true => "Nothing to search for.".to_string(),
+            false => format!("No commands match: {}", query),
+        }
+    } else {
+        let category = &args[0];
+        entries.retain(|entry| {
+            entry
+                .category
+                .as_deref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(category))
+        });
+        format!("No commands in category: {}", category)
+    };
+    if entries.is_empty() {
+        let message = context.error_theme.error.paint(missing);
+        return writeln!(context.error, "{}", message);
+    }
+
+    // Search results are kept in the order they were ranked
+    let help = Help::new(context.width);
+    let help = match search {
+        true => help.with_section(None, entries),
+        false => help.with_entries(None, entries),
+    };
+    help.render(context.output, context.theme)
+}
+
+/// A command which failed, along with what it is reported as.
+pub(crate) struct Failure {
+    /// The name of the command, including any namespaces it is within.
+    pub(crate) name: String,
+    /// The arguments given to the command.
+    pub(crate) args: Vec<String>,
+    /// Why it failed.
+    pub(crate) error: Box<dyn Error>,
+    /// Whether it has been reported already, as commands which couldn't be
+    /// run have.
+    pub(crate) reported: bool,
+}
+
+/// Runs a command which isn't built in, reporting why if it can't be run.
+/// If it fails, the failure is returned for the caller to report once any
+/// middleware around the namespaces it is within have seen it.
+///
+/// `prefix` is put before the name of the command in messages, for commands
+/// within namespaces.
+pub(crate) fn run<T>(
+    prefix: &str,
+    mut line: Vec<String>,
+    context: &mut Context<'_, '_, T>,
+    threshold: usize,
+) -> Result<(), Failure> {
+    if !find(prefix, &mut line, context, threshold) {
+        return Err(not_run(prefix, &line));
+    }
+    let confirmation = context.commands[&*line[0]].confirmation.clone();
+    if !confirm(confirmation.as_deref(), &mut line, context) {
+        return Ok(());
+    }
+
+    let name = format!("{}{}", prefix, line[0]);
+    let args = line[1..].to_vec();
+
+    // Looked up again, as the command may change the commands whilst running
+    let result = match context.commands[&*line[0]].command {
+        CommandType::Sync(c) => context.run(c, line),
+        #[cfg(feature = "async")]
+        CommandType::Async(a) => context.run_blocking(a, line),
+        CommandType::Context(c) => context.run_with_context(c, line),
+        CommandType::Namespace(_) => {
+            return context.run_namespace(prefix, line, threshold)
+        }
+    };
+    result.map_err(|error| Failure {
+        name,
+        args,
+        error,
+        reported: false,
+    })
+}
+
+/// Runs a command which isn't built in asynchronously, as [`run`] does.
+#[cfg(feature = "async")]
+pub(crate) async fn run_async<T: Send>(
+    prefix: &str,
+    mut line: Vec<String>,
+    context: &mut Context<'_, '_, T>,
+    threshold: usize,
+) -> Result<(), Failure> {
+    if !find(prefix, &mut line, context, threshold) {
+        return Err(not_run(prefix, &line));
+    }
+    let confirmation = context.commands[&*line[0]].confirmation.clone();
+    if !confirm(confirmation.as_deref(), &mut line, context) {
+        return Ok(());
+    }
+
+    let name = format!("{}{}", prefix, line[0]);
+    let args = line[1..].to_vec();
+
+    // Looked up again, as the command may change the commands whilst running
+    let result = match context.commands[&*line[0]].command {
+        CommandType::Sync(c) => context.run(c, line),
+        CommandType::Async(a) => context.run_async(a, line).await,
+        CommandType::Context(c) => context.run_with_context(c, line),
+        CommandType::Namespace(_) => {
+            return context.run_namespace_async(prefix, line, threshold).await
+        }
+    };
+    result.map_err(|error| Failure {
+        name,
+        args,
+        error,
+        reported: false,
+    })
+}
+
+/// Reports a command failing, unless it has been already, and gives back
+/// the error.
+pub(crate) fn report<T>(
+    context: &mut Context<'_, '_, T>,
+    failure: Failure,
+) -> Box<dyn Error> {
+    if !failure.reported {
+        let out = &mut *context.error;
+        let _ = context.reporter.command_failed(
+            out,
+            context.error_theme,
+            context.line,
+            &failure.name,
+            &failure.args,
+            &*failure.error,
+        );
+    }
+    failure.error
+}
+
+/// The failure of a command which couldn't be run, which [`find`] has
+/// reported.
+fn not_run(prefix: &str, line: &[String]) -> Failure {
+    let name = format!("{}{}", prefix, line[0]);
+    Failure {
+        error: format!("{} couldn't be run", name).into(),
+        name,
+        args: line[1..].to_vec(),
+        reported: true,
+    }
+}
+
+/// Checks there is a command which can be run, reporting why if there
+/// isn't one. Commands within namespaces are checked too, so that
+/// middleware never sees those which can't be run.
+///
+/// Abbreviated names are expanded in place, if abbreviations are turned on.
+pub(crate) fn find<T>(
+    prefix: &str,
+    line: &mut [String],
+    context: &mut Context<'_, '_, T>,
+    threshold: usize,
+) -> bool {
+    if !expand(prefix, line, context) {
+        return false;
+    }
+    let name = format!("{}{}", prefix, line[0]);
+    let command = match context.commands.get(&*line[0]) {
+        Some(command) => command,
+        None => {
+            let candidates = context
+                .builtins
+                .keys()
+                .chain(context.commands.keys())
+                .map(|name| &**name);
+            let suggestion = did_you_mean(&line[0], candidates, threshold);
+            let out = &mut *context.error;
+            let _ = context.reporter.not_found(
+                out,
+                context.error_theme,
+                &name,
+                &line[1..],
+                suggestion,
+            );
+            return false;
+        }
+    };
+    if let Err(reason) = command.available(context.state) {
+        let out = &mut *context.error;
+        let _ = context.reporter.unavailable(
+            out,
+            context.error_theme,
+            &name,
+            &line[1..],
+            &reason,
+        );
+        return false;
+    }
+    let nested = matches!(command.command, CommandType::Namespace(_));
+    if !nested || line.len() == 1 {
+        return true;
+    }
+
+    // Taken out whilst looking, as it is given the context
+    let (index, key, mut command) =
+        match context.commands.shift_remove_full(&*line[0]) {
+            Some(entry) => entry,
+            None => return false,
+        };
+    let found = match &mut command.command {
+        CommandType::Namespace(namespace) => {
+            let prefix = format!("{} ", name);
+            namespace.find(&prefix, context, &mut line[1..], threshold)
+        }
+        _ => true,
+    };
+    context.commands.shift_insert(index, key, command);
+    found
+}
+
+/// Replaces an abbreviated command name with the full name, if
+/// abbreviations are turned on. The shell does this for the names it is
+/// given, so this matters for those within namespaces.
+///
+/// Returns false if the abbreviation is ambiguous, in which case the
+/// candidates are reported.
+fn expand<T>(
+    prefix: &str,
+    line: &mut [String],
+    context: &mut Context<'_, '_, T>,
+) -> bool {
+    if !context.abbreviations {
+        return true;
+    }
+    let candidates = context
+        .builtins
+        .keys()
+        .chain(context.commands.keys())
+        .map(|name| &**name);
+    match suggest::expand(&line[0], candidates) {
+        Abbreviation::Unique(full) => line[0] = full.to_string(),
+        Abbreviation::Ambiguous(candidates) => {
+            let name = format!("{}{}", prefix, line[0]);
+            let out = &mut *context.error;
+            let _ = context.reporter.ambiguous(
+                out,
+                context.error_theme,
+                &name,
+                &candidates,
+            );
+            return false;
+        }
+        Abbreviation::Unknown => (),
+    }
+    true
+}
+
+/// Runs a built in, see [`Context::run_builtin`]. Returns whether the shell
+/// should quit.
+pub(crate) fn run_builtin<T>(
+    action: &Action,
+    args: &[String],
+    context: &mut Context<'_, '_, T>,
+) -> bool {
+    match action {
+        Action::Help => {
+            let _ = print_help(args, context);
+        }
+        Action::Quit => return true,
+        Action::Clear => {
+            let _ = write!(context.output, "\x1b[2J\x1b[1;1H");
+            let _ = context.output.flush();
+        }
+        Action::History => {
+            let history = context.input_handler.history();
+            for (i, line) in history.iter().enumerate() {
+                let _ = writeln!(context.output, "{:>5}  {}", i + 1, line);
+            }
+        }
+        Action::Version(version) => {
+            let _ = writeln!(context.output, "{}", version);
+        }
+        Action::Echo => {
+            let _ = writeln!(context.output, "{}", args.join(" "));
+        }
+    }
+    false
+}
+
+/// Asks for confirmation before running a command, if it needs it.
+/// Removes the `--yes` flag from the arguments if it is given.
+///
+/// Returns whether the command should be run.
+pub(crate) fn confirm<T>(
+    confirmation: Option<&str>,
+    args: &mut Vec<String>,
+    context: &mut Context<'_, '_, T>,
+) -> bool {
+    let question = match confirmation {
+        Some(question) => question,
+        None => return true,
+    };
+
+    // Check for `--yes`
+    if let Some(i) = args.iter().skip(1).position(|arg| arg == "--yes") {
+        args.remove(i + 1);
+        return true;
+    }
+    if context.batch {
+        return true;
+    }
+
+    match context.confirm(question) {
+        Ok(true) => true,
+        Ok(false) => {
+            let _ = writeln!(
+                context.error,
+                "{}",
+                context.error_theme.error.paint("Cancelled.")
+            );
+            false
+        }
+        Err(e) => {
+            let error = context.error_theme.error.paint(e);
+            let _ = writeln!(context.error, "{}", error);
+            false
+        }
+    }
+}
+
 pub mod default;
 pub use default::*;
 
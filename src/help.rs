@@ -0,0 +1,514 @@
+//! # Help
+//!
+//! The renderer behind the `help` built in of every handler. Help is made
+//! of sections, each with an optional heading, of commands and what they
+//! do. The names are aligned in a column, and their help is wrapped to the
+//! width of the output with a hanging indent. Commands with a
+//! [category](crate::Command::with_category) are grouped into a section
+//! for each:
+//!
+//! ```text
+//! Files:
+//!     open     opens a file, creating it if it doesn't exist and the
+//!              `--create` flag is given.
+//!     close    closes the file.
+//! ```
+//!
+//! Custom handlers can use it too:
+//!
+//! ```rust
+//! use shellfish::help::{Help, HelpEntry};
+//! use shellfish::Theme;
+//!
+//! let help = Help::new(40).with_section(
+//!     Some("Files:".to_string()),
+//!     vec![
+//!         HelpEntry::new("open".to_string(), "opens a file.".to_string()),
+//!         HelpEntry::new("close".to_string(), "closes it.".to_string()),
+//!     ],
+//! );
+//!
+//! let mut out = Vec::new();
+//! help.render(&mut out, &Theme::plain()).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(out).unwrap(),
+//!     "Files:\n    open     opens a file.\n    close    closes it.\n",
+//! );
+//! ```
+//!
+//! Widths are measured in terminal columns rather than characters, so
+//! names and help in any script line up.
+
+use std::cmp::Reverse;
+use std::io::{self, Write};
+
+use indexmap::IndexMap;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::builtin::Builtins;
+use crate::command::{CommandType, Commands};
+use crate::theme::{Style, Theme};
+use crate::Command;
+
+/// The width help is wrapped to when the output isn't a terminal.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// How far entries are indented.
+const INDENT: usize = 4;
+
+/// The space between the names and their help.
+const GAP: usize = 4;
+
+/// The narrowest the help may be wrapped to beside the names. Any narrower
+/// and the help goes below the names instead.
+const MIN_HELP_WIDTH: usize = 20;
+
+/// A line of the help.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HelpEntry {
+    /// The name of the command, including any namespaces it is in.
+    pub name: String,
+    /// What the command does.
+    pub help: String,
+    /// The arguments the command takes, if they are given.
+    pub usage: Option<String>,
+    /// A longer description of the command, which isn't shown in the list
+    /// of commands but is searched.
+    pub long_help: Option<String>,
+    /// Example lines which run the command, which are searched.
+    pub examples: Vec<String>,
+    /// Why the command can't be run, if it can't. Unavailable commands are
+    /// muted, with the reason after their help.
+    pub unavailable: Option<String>,
+    /// The category to group the command under, if any.
+    pub category: Option<String>,
+    /// Where the command comes within its category, higher first.
+    pub priority: i32,
+}
+
+impl HelpEntry {
+    /// Creates a new entry for an available command.
+    pub fn new(name: String, help: String) -> Self {
+        Self {
+            name,
+            help,
+            usage: None,
+            long_help: None,
+            examples: Vec::new(),
+            unavailable: None,
+            category: None,
+            priority: 0,
+        }
+    }
+}
+
+/// Some entries of the help, under an optional heading.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HelpSection {
+    /// The heading, such as `Files:`.
+    pub heading: Option<String>,
+    /// The entries, in the order they are shown.
+    pub entries: Vec<HelpEntry>,
+}
+
+/// Help which is ready to be rendered. See the
+/// [module level documentation](self).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Help {
+    /// The sections, which are separated by blank lines.
+    pub sections: Vec<HelpSection>,
+    /// The width to wrap to, in columns.
+    pub width: usize,
+}
+
+impl Help {
+    /// Creates help without any sections, which wraps to `width`.
+    pub fn new(width: usize) -> Self {
+        Self {
+            sections: Vec::new(),
+            width,
+        }
+    }
+
+    /// Adds a section to the end of the help.
+    pub fn with_section(
+        mut self,
+        heading: Option<String>,
+        entries: Vec<HelpEntry>,
+    ) -> Self {
+        self.sections.push(HelpSection { heading, entries });
+        self
+    }
+
+    /// Adds entries, grouped into a section for each category in the order
+    /// the categories first appear. Entries without a category go in a
+    /// section under `heading`. Within each section, entries with a higher
+    /// priority come first, and the order is otherwise kept.
+    pub fn with_entries(
+        mut self,
+        heading: Option<String>,
+        entries: Vec<HelpEntry>,
+    ) -> Self {
+        for (category, entries) in categorise(entries) {
+            let heading = match category {
+                Some(category) => Some(format!("{}:", category)),
+                None => heading.clone(),
+            };
+            self = self.with_section(heading, entries);
+        }
+        self
+    }
+
+    /// Writes the help to `out`, coloured with `theme`.
+    ///
+    /// The names of every section share a column, so that the sections
+    /// line up with each other.
+    pub fn render(&self, out: &mut dyn Write, theme: &Theme) -> io::Result<()> {
+        let name_width = self
+            .sections
+            .iter()
+            .flat_map(|section| &section.entries)
+            .map(|entry| entry.name.width())
+            .max()
+            .unwrap_or(0);
+
+        // Put the help below the names if there isn't room beside them
+        let beside = INDENT + name_width + GAP;
+        let fits = self.width.saturating_sub(beside) >= MIN_HELP_WIDTH;
+        let indent = if fits { beside } else { INDENT * 2 };
+        let help_width = self.width.saturating_sub(indent).max(MIN_HELP_WIDTH);
+
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            if let Some(heading) = &section.heading {
+                writeln!(out, "{}", theme.heading.paint(heading))?;
+            }
+
+            for entry in &section.entries {
+                let (name_style, help_style, help) = match &entry.unavailable {
+                    None => {
+                        (theme.command, Style::default(), entry.help.clone())
+                    }
+                    Some(reason) => (
+                        theme.muted,
+                        theme.muted,
+                        format!("{} ({})", entry.help, reason),
+                    ),
+                };
+
+                // The name, then the first line of the help beside it
+                let name = name_style.paint(&entry.name);
+                write!(out, "{}{}", spaces(INDENT), name)?;
+                let lines = wrap(&help, help_width);
+                let mut lines = lines.iter();
+                if fits {
+                    let padding = name_width - entry.name.width() + GAP;
+                    match lines.next() {
+                        Some(line) => writeln!(
+                            out,
+                            "{}{}",
+                            spaces(padding),
+                            help_style.paint(line)
+                        )?,
+                        None => writeln!(out)?,
+                    }
+                } else {
+                    writeln!(out)?;
+                }
+
+                // Then the rest, with a hanging indent
+                for line in lines {
+                    writeln!(
+                        out,
+                        "{}{}",
+                        spaces(indent),
+                        help_style.paint(line)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Gets the help for the built ins.
+pub(crate) fn builtin_entries(builtins: &Builtins<'_>) -> Vec<HelpEntry> {
+    builtins
+        .iter()
+        .map(|(name, builtin)| {
+            HelpEntry::new(name.to_string(), builtin.help.clone())
+        })
+        .collect()
+}
+
+/// Gets the help for some commands, including those within namespaces.
+/// Commands which are hidden whilst unavailable are left out.
+pub(crate) fn help_entries<T>(
+    commands: &Commands<'_, T>,
+    state: &T,
+) -> Vec<HelpEntry> {
+    let mut entries = Vec::new();
+    for (name, command) in commands {
+        let unavailable = command.available(state).err();
+        if unavailable.is_some() && command.hide_when_unavailable {
+            continue;
+        }
+
+        // Look into available namespaces
+        let inner = match (&unavailable, &command.command) {
+            (None, CommandType::Namespace(namespace)) => namespace.help(state),
+            _ => Vec::new(),
+        };
+        push_entries(&mut entries, name, command, unavailable, inner);
+    }
+    entries
+}
+
+/// Gets the entries of every command, including those within namespaces,
+/// whether or not they are available. This is for reference documentation,
+/// where there is no state.
+pub(crate) fn reference_entries<T>(
+    commands: &Commands<'_, T>,
+) -> Vec<HelpEntry> {
+    let mut entries = Vec::new();
+    for (name, command) in commands {
+        let inner = match &command.command {
+            CommandType::Namespace(namespace) => namespace.reference(),
+            _ => Vec::new(),
+        };
+        push_entries(&mut entries, name, command, None, inner);
+    }
+    entries
+}
+
+/// Adds the entry of a command, followed by those of the commands within it
+/// if it is a namespace. These are kept with it, sharing its category and
+/// priority.
+fn push_entries<T>(
+    entries: &mut Vec<HelpEntry>,
+    name: &str,
+    command: &Command<T>,
+    unavailable: Option<String>,
+    inner: Vec<HelpEntry>,
+) {
+    entries.push(HelpEntry {
+        name: name.to_string(),
+        help: command.help.clone(),
+        usage: command.usage.clone(),
+        long_help: command.long_help.clone(),
+        examples: command.examples.clone(),
+        unavailable,
+        category: command.category.clone(),
+        priority: command.priority,
+    });
+    entries.extend(inner.into_iter().map(|entry| HelpEntry {
+        name: format!("{} {}", name, entry.name),
+        category: command.category.clone(),
+        priority: command.priority,
+        ..entry
+    }));
+}
+
+/// Groups entries by their category, in the order the categories first
+/// appear, with the entries of each ordered by priority.
+pub(crate) fn categorise(
+    entries: Vec<HelpEntry>,
+) -> IndexMap<Option<String>, Vec<HelpEntry>> {
+    let mut categories: IndexMap<_, Vec<_>> = IndexMap::new();
+    for entry in entries {
+        categories
+            .entry(entry.category.clone())
+            .or_default()
+            .push(entry);
+    }
+    for entries in categories.values_mut() {
+        entries.sort_by_key(|entry| Reverse(entry.priority));
+    }
+    categories
+}
+
+/// Finds the entries which match a search, best first.
+///
+/// Each word of the query is looked for, ignoring case, in the names, help,
+/// long help and examples of the entries. Entries must match every word,
+/// and are ranked by where the words are found: matching a name counts for
+/// the most, then the help, the long help and finally the examples.
+///
+/// ```rust
+/// use shellfish::help::{self, HelpEntry};
+///
+/// let entries = vec![
+///     HelpEntry::new("open".to_string(), "opens a file.".to_string()),
+///     HelpEntry::new("file".to_string(), "shows a file.".to_string()),
+///     HelpEntry::new("quit".to_string(), "quits.".to_string()),
+/// ];
+/// let found = help::search(entries, "file");
+/// assert_eq!(found[0].name, "file");
+/// assert_eq!(found[1].name, "open");
+/// assert_eq!(found.len(), 2);
+/// ```
+pub fn search(entries: Vec<HelpEntry>, query: &str) -> Vec<HelpEntry> {
+    let words: Vec<_> = query
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+
+    let mut found: Vec<_> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let mut total = 0;
+            for word in &words {
+                match relevance(&entry, word) {
+                    0 => return None,
+                    score => total += score,
+                }
+            }
+            Some((total, entry))
+        })
+        .collect();
+    found.sort_by_key(|(score, _)| Reverse(*score));
+    found.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// How well a word of a search matches an entry, where `0` is not at all.
+/// The word is already in lower case.
+fn relevance(entry: &HelpEntry, word: &str) -> u32 {
+    let contains = |text: &str| text.to_lowercase().contains(word);
+    let name = entry.name.to_lowercase();
+    let mut score = if name == word {
+        100
+    } else if name.starts_with(word) {
+        60
+    } else if name.contains(word) {
+        40
+    } else {
+        0
+    };
+    if contains(&entry.help) {
+        score += 20;
+    }
+    if entry.long_help.as_deref().is_some_and(contains) {
+        score += 10;
+    }
+    if entry.examples.iter().any(|example| contains(example)) {
+        score += 5;
+    }
+    score
+}
+
+/// Wraps text into lines no wider than `width` columns where possible,
+/// breaking between words. Words wider than a line are left whole, and
+/// line breaks in the text are kept.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.width() + 1 + word.width() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// A run of spaces.
+fn spaces(count: usize) -> String {
+    " ".repeat(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_between_words() {
+        assert_eq!(wrap("one two three four", 9), ["one two", "three", "four"]);
+        assert_eq!(wrap("one two", 7), ["one two"]);
+        assert_eq!(wrap("  spaced   out  ", 20), ["spaced out"]);
+    }
+
+    #[test]
+    fn leaves_long_words_whole() {
+        assert_eq!(
+            wrap("a extraordinarily b", 5),
+            ["a", "extraordinarily", "b"]
+        );
+    }
+
+    #[test]
+    fn keeps_line_breaks() {
+        assert_eq!(
+            wrap("first\n\nsecond line", 6),
+            ["first", "", "second", "line"]
+        );
+    }
+
+    #[test]
+    fn measures_wide_characters_by_their_columns() {
+        assert_eq!(wrap("日本 語", 5), ["日本", "語"]);
+        assert_eq!(wrap("日本 語", 7), ["日本 語"]);
+    }
+
+    fn entry(name: &str, category: Option<&str>, priority: i32) -> HelpEntry {
+        HelpEntry {
+            category: category.map(str::to_string),
+            priority,
+            ..HelpEntry::new(name.to_string(), String::new())
+        }
+    }
+
+    #[test]
+    fn groups_by_category_in_order_of_appearance() {
+        let entries = vec![
+            entry("open", Some("Files"), 0),
+            entry("quit", None, 0),
+            entry("save", Some("Files"), 0),
+            entry("ping", Some("Network"), 0),
+        ];
+        let categories = categorise(entries);
+        let names: Vec<(Option<&str>, Vec<&str>)> = categories
+            .iter()
+            .map(|(category, entries)| {
+                let names = entries.iter().map(|entry| &*entry.name);
+                (category.as_deref(), names.collect())
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (Some("Files"), vec!["open", "save"]),
+                (None, vec!["quit"]),
+                (Some("Network"), vec!["ping"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn orders_by_priority_then_as_given() {
+        let entries = vec![
+            entry("low", None, -1),
+            entry("first", None, 0),
+            entry("high", None, 5),
+            entry("second", None, 0),
+        ];
+        let names: Vec<String> = categorise(entries)
+            .swap_remove(&None)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["high", "first", "second", "low"]);
+    }
+}
@@ -0,0 +1,755 @@
+//! # Helper
+//!
+//! A [`rustyline`] helper which knows about the commands of a shell. Create
+//! one from the shell which it will serve, and give it to the editor:
+//!
+//! ```rust
+//! use rustyline::{history::DefaultHistory, Editor};
+//! use shellfish::{handler::DefaultHandler, Shell, ShellfishHelper};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut shell = Shell::new_with_handler(
+//!         (),
+//!         "[Shell]-$ ",
+//!         DefaultHandler::default(),
+//!         Editor::<ShellfishHelper<()>, DefaultHistory>::new()?,
+//!     );
+//!
+//!     let helper = ShellfishHelper::new(&shell);
+//!     shell.input_handler.set_helper(Some(helper));
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The helper does not keep a copy of the commands, instead the shell lends
+//! them to it whilst a line is being read. This means that commands added
+//! after the editor was created are picked up too.
+
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::{Hint, Hinter, HistoryHinter};
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use yansi::Style;
+
+use crate::builtin::Builtins;
+use crate::command::Commands;
+use crate::shell::{escape, lex, unescape, Lexeme, Piece};
+use crate::suggest::{self, Abbreviation};
+use crate::*;
+
+/// What a running shell lends to its helpers whilst reading a line.
+pub(crate) struct Session<'s, 'a, T> {
+    pub(crate) builtins: &'s Builtins<'a>,
+    pub(crate) commands: &'s Commands<'a, T>,
+    pub(crate) state: &'s T,
+    pub(crate) abbreviations: bool,
+    /// The shell's theme, already resolved for its output.
+    pub(crate) theme: Theme,
+}
+
+impl<T> Session<'_, '_, T> {
+    /// Gets the full name of a command or built in, which may be
+    /// abbreviated.
+    fn resolve(&self, name: &str) -> Option<&str> {
+        let mut candidates = self
+            .builtins
+            .keys()
+            .map(|name| &**name)
+            .chain(self.commands.keys().map(|name| &**name));
+        if !self.abbreviations {
+            return candidates.find(|candidate| *candidate == name);
+        }
+        match suggest::expand(name, candidates) {
+            Abbreviation::Unique(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Finds the command with the given name, which may be abbreviated.
+    fn command(&self, name: &str) -> Option<&Command<T>> {
+        self.commands.get(self.resolve(name)?)
+    }
+}
+
+/// The session currently being lent out, along with the thread it is lent
+/// on. The pointer is to a `Session<'_, '_, T>`.
+///
+/// The pointer is only set by [`Attachment::lend`], which takes it back
+/// before returning (or unwinding), so whilst it is set the session it
+/// points to is alive and borrowed immutably.
+struct Slot<T>(Option<(ThreadId, NonNull<()>)>, PhantomData<T>);
+
+// SAFETY: The slot is shared with helpers, which rustyline may move to
+// other threads, but the pointer is only dereferenced by
+// `Attachment::with` on the thread it was lent on. The session is never
+// touched from any other thread, so neither it nor `T` need be `Send` or
+// `Sync`.
+unsafe impl<T> Send for Slot<T> {}
+
+/// Shared between a shell and every helper created from it.
+pub(crate) struct Attachment<T>(Arc<Mutex<Slot<T>>>);
+
+impl<T> Attachment<T> {
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(Mutex::new(Slot(None, PhantomData))))
+    }
+
+    /// Lends the session to the helpers whilst `f` runs.
+    ///
+    /// Taking a closure rather than returning a guard means the session
+    /// can't be left lent out, such as by forgetting the guard.
+    pub(crate) fn lend<R>(
+        &self,
+        session: &Session<'_, '_, T>,
+        f: impl FnOnce() -> R,
+    ) -> R {
+        let pointer = NonNull::from(session).cast();
+        let previous = self.slot().0.replace((thread::current().id(), pointer));
+        let _lend = Lend(self, previous);
+        f()
+    }
+
+    /// Calls `f` with the session, if one is being lent on this thread.
+    fn with<R>(&self, f: impl FnOnce(&Session<'_, '_, T>) -> R) -> Option<R> {
+        let slot = self.slot();
+        match slot.0 {
+            // SAFETY: The session is alive, as `lend` is still running on
+            // this thread (see `Slot`). The lock is held for the whole of
+            // `f`, so it can't be taken back in the meantime, and as `f`
+            // works for any lifetimes it can't keep the reference.
+            Some((thread, pointer)) if thread == thread::current().id() => {
+                Some(f(unsafe { pointer.cast().as_ref() }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Locks the slot. A completer which panicked whilst the lock was held
+    /// leaves the slot as it was, so a poisoned lock can still be used.
+    fn slot(&self) -> MutexGuard<'_, Slot<T>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> Clone for Attachment<T> {
+    /// A cloned shell gets its own attachment, the helpers stay with the
+    /// original.
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+/// Takes the session back from the helpers when dropped, restoring any
+/// session which was lent before it.
+struct Lend<'p, T>(&'p Attachment<T>, Option<(ThreadId, NonNull<()>)>);
+
+impl<T> Drop for Lend<'_, T> {
+    fn drop(&mut self) {
+        self.0.slot().0 = self.1.take();
+    }
+}
+
+/// A [`rustyline::Helper`] for shellfish shells.
+///
+/// It completes the built-in commands and the names of the commands in
+/// [`Shell::commands`](crate::Shell::commands). The arguments of a command
+/// are completed by its [`completer`](crate::Command::with_completer), if
+/// it has one. Commands which are [unavailable](crate::Command::with_guard)
+/// are not completed.
+///
+/// The line is highlighted as it is typed with the shell's
+/// [`theme`](crate::Shell::theme), using the same rules as the shell uses
+/// to split it: known commands are coloured as commands, unknown ones as
+/// errors, and strings and escape sequences have their own colours.
+/// Invalid escapes and unclosed strings are underlined as errors.
+///
+/// Whilst typing, the rest of a matching line from the history is hinted
+/// after the cursor. Failing that, once the command name has been typed the
+/// rest of its [`usage`](crate::Command::usage) is hinted.
+pub struct ShellfishHelper<T> {
+    session: Attachment<T>,
+}
+
+impl<T> ShellfishHelper<T> {
+    /// Creates a new helper for the given shell.
+    pub fn new<M: Display, H, I: InputHandler>(
+        shell: &Shell<'_, T, M, H, I>,
+    ) -> Self {
+        Self {
+            session: Attachment(shell.attachment.0.clone()),
+        }
+    }
+}
+
+impl<T> Completer for ShellfishHelper<T> {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(self
+            .session
+            .with(|session| complete_line(session, &line[..pos]))
+            .unwrap_or((pos, Vec::new())))
+    }
+}
+
+/// Completes the argument at the end of a line, giving where it starts.
+fn complete_line<T>(
+    session: &Session<'_, '_, T>,
+    line: &str,
+) -> (usize, Vec<Pair>) {
+    // The shell trims the line before it is split, so do the same
+    let offset = line.len() - line.trim_start().len();
+    let (pieces, _) = lex(&line[offset..]);
+
+    // Find the argument under the cursor, which may be quoted or escaped
+    let (argument, start) = match pieces.last() {
+        None => (0, offset),
+        Some(last) if last.lexeme == Lexeme::Separator => {
+            (last.argument + 1, offset + last.span.end)
+        }
+        Some(last) => {
+            let first = pieces
+                .iter()
+                .find(|piece| piece.argument == last.argument)
+                .unwrap_or(last);
+            (last.argument, offset + first.span.start)
+        }
+    };
+    let partial = match word(&pieces, argument) {
+        Some(partial) => partial,
+        None => return (start, Vec::new()),
+    };
+
+    let candidates = match argument {
+        0 => complete_name(session, &partial),
+        _ => complete_argument(session, &line[offset..start - 1], &partial),
+    };
+    (start, candidates)
+}
+
+/// Gets an argument from the pieces of a line, without its quotes and
+/// escapes. There is none if it has an invalid escape.
+fn word(pieces: &[Piece], argument: usize) -> Option<String> {
+    let mut word = String::new();
+    for piece in pieces.iter().filter(|piece| piece.argument == argument) {
+        match piece.lexeme {
+            Lexeme::Char(c) | Lexeme::Escape(c) => word.push(c),
+            Lexeme::InvalidEscape(_) => return None,
+            Lexeme::Quote | Lexeme::Separator => (),
+        }
+    }
+    Some(word)
+}
+
+/// Completes the name of a command, leaving out unavailable commands.
+fn complete_name<T>(session: &Session<'_, '_, T>, word: &str) -> Vec<Pair> {
+    let available = session
+        .commands
+        .iter()
+        .filter(|(_, command)| command.available(session.state).is_ok())
+        .map(|(name, _)| &**name);
+
+    session
+        .builtins
+        .keys()
+        .map(|name| &**name)
+        .chain(available)
+        .filter(|name| name.starts_with(word))
+        .map(|name| Pair {
+            display: name.to_string(),
+            replacement: format!("{} ", name),
+        })
+        .collect()
+}
+
+/// Completes an argument using the command's completer, given the line
+/// before it.
+fn complete_argument<T>(
+    session: &Session<'_, '_, T>,
+    before: &str,
+    partial: &str,
+) -> Vec<Pair> {
+    let args = match unescape(before) {
+        Ok(args) => args,
+        Err(_) => return Vec::new(),
+    };
+
+    // Quotes or escapes alone may leave no name, such as in `"" x`
+    let name = match args.first() {
+        Some(name) => name,
+        None => return Vec::new(),
+    };
+    let command = match session
+        .command(name)
+        .filter(|command| command.available(session.state).is_ok())
+    {
+        Some(command) => command,
+        None => return Vec::new(),
+    };
+
+    command
+        .complete(session.state, &args, partial, session.abbreviations)
+        .into_iter()
+        .map(|completion| {
+            let mut replacement = escape(&completion.value);
+            if !replacement.ends_with('/') {
+                replacement.push(' ');
+            }
+            Pair {
+                display: match completion.description {
+                    Some(description) => {
+                        format!("{} - {}", completion.value, description)
+                    }
+                    None => completion.value,
+                },
+                replacement,
+            }
+        })
+        .collect()
+}
+
+/// A hint shown after the cursor by [`ShellfishHelper`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ShellfishHint {
+    /// The rest of a line from the history, which can be accepted.
+    History(String),
+    /// The rest of a command's usage string, which is only shown.
+    Usage(String),
+}
+
+impl Hint for ShellfishHint {
+    fn display(&self) -> &str {
+        match self {
+            ShellfishHint::History(hint) | ShellfishHint::Usage(hint) => hint,
+        }
+    }
+
+    fn completion(&self) -> Option<&str> {
+        match self {
+            ShellfishHint::History(hint) => Some(hint),
+            ShellfishHint::Usage(_) => None,
+        }
+    }
+}
+
+impl<T> Hinter for ShellfishHelper<T> {
+    type Hint = ShellfishHint;
+
+    fn hint(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> Option<ShellfishHint> {
+        // Prefer the history
+        if let Some(hint) = HistoryHinter::new().hint(line, pos, ctx) {
+            return Some(ShellfishHint::History(hint));
+        }
+        if pos < line.len() {
+            return None;
+        }
+
+        // Otherwise show the rest of the usage
+        let (pieces, _) = lex(line.trim_start());
+        let last = pieces.last()?;
+        let name: String = pieces
+            .iter()
+            .take_while(|piece| piece.argument == 0)
+            .filter_map(|piece| match piece.lexeme {
+                Lexeme::Char(c) | Lexeme::Escape(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        let usage = self
+            .session
+            .with(|session| session.command(&name)?.usage.clone())
+            .flatten()?;
+
+        // Skip the arguments which have been given
+        let (given, space) = match last.lexeme {
+            Lexeme::Separator => (last.argument, ""),
+            _ if last.argument == 0 => (0, " "),
+            _ => return None,
+        };
+        let rest: Vec<&str> = usage.split_whitespace().skip(given).collect();
+        if rest.is_empty() {
+            return None;
+        }
+        Some(ShellfishHint::Usage(format!("{}{}", space, rest.join(" "))))
+    }
+}
+
+impl<T> ShellfishHelper<T> {
+    /// The theme of the shell being served, or a plain one if it isn't
+    /// reading.
+    fn theme(&self) -> Theme {
+        self.session
+            .with(|session| session.theme)
+            .unwrap_or_else(Theme::plain)
+    }
+}
+
+impl<T> Highlighter for ShellfishHelper<T> {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        let theme = self.theme();
+        Cow::Owned(theme.muted.paint(hint).to_string())
+    }
+
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        // The shell trims the line before it is split, so do the same
+        let offset = line.len() - line.trim_start().len();
+        let trimmed = line.trim();
+        let (pieces, unclosed) = lex(trimmed);
+        if pieces.is_empty() {
+            return Cow::Borrowed(line);
+        }
+
+        // Find out whether the command exists
+        let name: String = pieces
+            .iter()
+            .take_while(|piece| piece.argument == 0)
+            .filter_map(|piece| match piece.lexeme {
+                Lexeme::Char(c) | Lexeme::Escape(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        let known = self
+            .session
+            .with(|session| session.resolve(&name).is_some());
+        let theme = self.theme();
+
+        // An unclosed string is shown from its opening quote
+        let unclosed = match unclosed {
+            true => pieces
+                .iter()
+                .rposition(|piece| piece.lexeme == Lexeme::Quote),
+            false => None,
+        };
+
+        let mut highlighted = String::with_capacity(line.len() * 2);
+        highlighted.push_str(&line[..offset]);
+
+        // Paint runs of pieces with the same style together
+        let mut run: Option<(Option<Style>, Range<usize>)> = None;
+        for (i, piece) in pieces.iter().enumerate() {
+            let style = match piece.lexeme {
+                Lexeme::InvalidEscape(_) => Some(theme.invalid),
+                _ if Some(i) == unclosed => Some(theme.invalid),
+                Lexeme::Separator => None,
+                _ if piece.argument == 0 => match known {
+                    Some(true) => Some(theme.command),
+                    Some(false) => Some(theme.unknown),
+                    None => None,
+                },
+                Lexeme::Escape(_) => Some(theme.escape),
+                Lexeme::Quote => Some(theme.string),
+                _ if piece.quoted => Some(theme.string),
+                Lexeme::Char(_) => None,
+            };
+
+            match &mut run {
+                Some((run_style, span)) if *run_style == style => {
+                    span.end = piece.span.end;
+                }
+                _ => {
+                    if let Some((style, span)) = run.take() {
+                        paint(&mut highlighted, style, &trimmed[span]);
+                    }
+                    run = Some((style, piece.span.clone()));
+                }
+            }
+        }
+        if let Some((style, span)) = run {
+            paint(&mut highlighted, style, &trimmed[span]);
+        }
+
+        highlighted.push_str(&line[offset + trimmed.len()..]);
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _forced: bool) -> bool {
+        true
+    }
+}
+
+/// Pushes some text onto a line, painting it if there is a style.
+fn paint(line: &mut String, style: Option<Style>, text: &str) {
+    match style {
+        Some(style) => line.push_str(&style.paint(text).to_string()),
+        None => line.push_str(text),
+    }
+}
+
+impl<T> Validator for ShellfishHelper<T> {}
+
+impl<T> Helper for ShellfishHelper<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::namespace::Namespace;
+    use rustyline::history::{DefaultHistory, History};
+    use std::panic::{self, AssertUnwindSafe};
+
+    fn commands() -> Commands<'static, ()> {
+        let mut commands = Commands::new();
+        commands.insert(
+            "set".into(),
+            Command::new("sets the mode.".to_string(), |_, _| Ok(()))
+                .with_usage("<speed> [when]".to_string())
+                .with_completer(|_, _, partial| {
+                    completion::values(["fast", "slow"], partial)
+                }),
+        );
+        commands.insert(
+            "greet".into(),
+            Command::new("greets someone.".to_string(), |_, _| Ok(()))
+                .with_completer(|_, args, partial| {
+                    // Say how many arguments came before
+                    completion::values(["a b", "abc"], partial)
+                        .into_iter()
+                        .map(|value| {
+                            let count = args.len().to_string();
+                            completion::Completion::with_description(
+                                value.value,
+                                count,
+                            )
+                        })
+                        .collect()
+                }),
+        );
+        let mut mode = Commands::new();
+        mode.insert("set".into(), commands["set"].clone());
+        commands.insert(
+            "mode".into(),
+            Command::new_namespace(
+                "changes the mode.".to_string(),
+                Namespace::new(mode, |state| state, |state| state),
+            ),
+        );
+        commands
+    }
+
+    /// Completes a line, giving where the completions start and their
+    /// displays and replacements.
+    fn complete_at(
+        line: &str,
+        abbreviations: bool,
+    ) -> (usize, Vec<(String, String)>) {
+        let builtins = builtin::defaults();
+        let commands = commands();
+        let session = Session {
+            builtins: &builtins,
+            commands: &commands,
+            state: &(),
+            abbreviations,
+            theme: Theme::plain(),
+        };
+        let (start, pairs) = complete_line(&session, line);
+        let pairs = pairs
+            .into_iter()
+            .map(|pair| (pair.display, pair.replacement))
+            .collect();
+        (start, pairs)
+    }
+
+    fn complete(line: &str, abbreviations: bool) -> Vec<String> {
+        let (_, pairs) = complete_at(line, abbreviations);
+        pairs
+            .into_iter()
+            .map(|(_, replacement)| replacement)
+            .collect()
+    }
+
+    /// Hints a line with the cursor at `pos`, given the history.
+    fn hint_at(
+        line: &str,
+        pos: usize,
+        history: &[&str],
+    ) -> Option<ShellfishHint> {
+        let builtins = builtin::defaults();
+        let commands = commands();
+        let session = Session {
+            builtins: &builtins,
+            commands: &commands,
+            state: &(),
+            abbreviations: false,
+            theme: Theme::plain(),
+        };
+        let mut entries = DefaultHistory::new();
+        for entry in history {
+            entries.add(entry).unwrap();
+        }
+        let attachment = Attachment::new();
+        let helper = ShellfishHelper {
+            session: Attachment(attachment.0.clone()),
+        };
+        attachment
+            .lend(&session, || helper.hint(line, pos, &Context::new(&entries)))
+    }
+
+    fn hint(line: &str) -> Option<ShellfishHint> {
+        hint_at(line, line.len(), &[])
+    }
+
+    #[test]
+    fn lends_sessions_only_on_their_thread() {
+        let builtins = builtin::defaults();
+        let commands = commands();
+        let session = Session {
+            builtins: &builtins,
+            commands: &commands,
+            state: &(),
+            abbreviations: false,
+            theme: Theme::plain(),
+        };
+        let attachment = Attachment::new();
+        let helper = Attachment(attachment.0.clone());
+        let count = |session: &Session<'_, '_, ()>| session.commands.len();
+
+        assert_eq!(helper.with(count), None);
+        attachment.lend(&session, || {
+            assert_eq!(helper.with(count), Some(3));
+            thread::scope(|scope| {
+                scope.spawn(|| assert_eq!(helper.with(count), None));
+            });
+            assert_eq!(helper.with(count), Some(3));
+
+            // Clones belong to cloned shells, which lend their own sessions
+            assert_eq!(attachment.clone().with(count), None);
+        });
+        assert_eq!(helper.with(count), None);
+    }
+
+    #[test]
+    fn lends_sessions_after_a_completer_panics() {
+        let builtins = builtin::defaults();
+        let commands = commands();
+        let session = Session {
+            builtins: &builtins,
+            commands: &commands,
+            state: &(),
+            abbreviations: false,
+            theme: Theme::plain(),
+        };
+        let attachment = Attachment::new();
+        let count = |session: &Session<'_, '_, ()>| session.commands.len();
+
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+            attachment.lend(&session, || {
+                attachment.with(|_| panic!("the completer panicked"))
+            })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(attachment.with(count), None);
+        assert_eq!(
+            attachment.lend(&session, || attachment.with(count)),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn completes_arguments() {
+        assert_eq!(complete("set f", false), ["fast "]);
+        assert_eq!(complete("set ", false), ["fast ", "slow "]);
+        assert!(complete("unknown f", false).is_empty());
+    }
+
+    #[test]
+    fn completes_names() {
+        let greet = ("greet".to_string(), "greet ".to_string());
+        assert_eq!(complete_at("gr", false), (0, vec![greet]));
+        assert_eq!(complete_at("  gr", false).0, 2);
+        assert!(complete("x", false).is_empty());
+    }
+
+    #[test]
+    fn completes_escaped_partial_arguments() {
+        let a_b = ("a b - 1".to_string(), r"a\ b ".to_string());
+        assert_eq!(complete_at(r"greet a\ ", false), (6, vec![a_b]));
+        assert_eq!(complete_at(r"greet a\", false).0, 6);
+        assert_eq!(complete_at(r"greet \", false).0, 6);
+        assert!(complete(r"greet \q", false).is_empty());
+
+        // Earlier arguments are unescaped too
+        let (start, pairs) = complete_at(r"greet x\ y a", false);
+        assert_eq!(start, 11);
+        assert_eq!(pairs[0].0, "a b - 2");
+    }
+
+    #[test]
+    fn completes_quoted_partial_arguments() {
+        let a_b = ("a b - 1".to_string(), r"a\ b ".to_string());
+        assert_eq!(complete_at(r#"greet "a "#, false), (6, vec![a_b]));
+        assert_eq!(complete(r#"greet "a"#, false), [r"a\ b ", "abc "]);
+        assert_eq!(complete(r#"greet "x y" a"#, false), [r"a\ b ", "abc "]);
+        assert!(complete(r#"greet "b"#, false).is_empty());
+    }
+
+    #[test]
+    fn completes_abbreviated_commands_within_namespaces() {
+        assert_eq!(complete("mode set f", false), ["fast "]);
+        assert!(complete("mode s f", false).is_empty());
+        assert_eq!(complete("mode s f", true), ["fast "]);
+        assert_eq!(complete("mo s ", true), ["fast ", "slow "]);
+    }
+
+    #[test]
+    fn hints_the_rest_of_the_usage() {
+        let usage = |hint: &str| Some(ShellfishHint::Usage(hint.to_string()));
+        assert_eq!(hint("set"), usage(" <speed> [when]"));
+        assert_eq!(hint("set "), usage("<speed> [when]"));
+        assert_eq!(hint("  set "), usage("<speed> [when]"));
+        assert_eq!(hint("mode "), None);
+        assert_eq!(hint("unknown "), None);
+    }
+
+    #[test]
+    fn hints_skip_the_given_arguments() {
+        let usage = |hint: &str| Some(ShellfishHint::Usage(hint.to_string()));
+        assert_eq!(hint("set fast "), usage("[when]"));
+        assert_eq!(hint("  set fast "), usage("[when]"));
+        assert_eq!(hint(r"set a\ b "), usage("[when]"));
+        assert_eq!(hint(r#"set "a b" "#), usage("[when]"));
+
+        // Nothing is hinted mid-argument, mid-line or past the usage
+        assert_eq!(hint("set f"), None);
+        assert_eq!(hint("set fast now "), None);
+        assert_eq!(hint_at("set ", 2, &[]), None);
+    }
+
+    #[test]
+    fn hints_prefer_the_history() {
+        let history = ["set fast now"];
+        assert_eq!(
+            hint_at("set ", 4, &history),
+            Some(ShellfishHint::History("fast now".to_string()))
+        );
+        assert_eq!(
+            hint_at("set slow ", 9, &history),
+            Some(ShellfishHint::Usage("[when]".to_string()))
+        );
+    }
+
+    #[test]
+    fn completes_nothing_without_a_name() {
+        assert!(complete("\"\" x", false).is_empty());
+        assert!(complete("\"\" ", false).is_empty());
+        assert!(complete("\\  x", false).is_empty());
+        assert!(complete("\\ ", false).is_empty());
+    }
+}
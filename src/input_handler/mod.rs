@@ -1,11 +1,22 @@
 #[cfg(feature = "rustyline")]
 use rustyline::error::ReadlineError;
+#[cfg(feature = "rustyline")]
+use rustyline::history::SearchDirection;
 use std::io::{self, stdin, stdout, Write};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::thread::{self, ThreadId};
 
 /// A trait for anything that can be used to gain user input
 pub trait InputHandler {
     /// Reads user input
     fn read(&mut self, prompt: &str) -> io::Result<InputResult>;
+
+    /// Gets the lines which have been read, oldest first, if they are
+    /// kept.
+    fn history(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 pub enum InputResult {
@@ -14,6 +25,92 @@ pub enum InputResult {
     S(String),
 }
 
+/// The input handler lent to a [`Context`](crate::Context), which commands
+/// can ask questions through.
+///
+/// Synchronous shells don't need their input handler to be `Send`, so
+/// theirs can only be used on the thread running the shell. Reading from
+/// another thread fails, and the history there is empty.
+///
+/// This is a `&'s mut dyn InputHandler` which has forgotten whether the
+/// handler is `Send`, so that it can be kept in a context which is.
+pub struct Input<'s> {
+    /// Borrowed mutably for `'s`, which the marker records.
+    handler: NonNull<dyn InputHandler + 's>,
+    /// The thread the handler is lent on, if it can't be sent to others.
+    thread: Option<ThreadId>,
+    marker: PhantomData<&'s mut (dyn InputHandler + 's)>,
+}
+
+// SAFETY: Sending an `Input` sends the exclusive borrow of its handler.
+// That is sound if the handler is `Send`, which `new` requires. Otherwise
+// `local` records the thread it was lent on, and `read` and `history` are
+// the only places the pointer is dereferenced, and they check the thread
+// first. Thread ids are never reused, so a handler which isn't `Send` is
+// only ever used on its own thread. `Input` isn't `Sync`, and `reborrow`
+// borrows it mutably, so only one `Input` can use a handler at a time.
+unsafe impl Send for Input<'_> {}
+
+impl<'s> Input<'s> {
+    /// Lends an input handler, which can be used on any thread.
+    #[cfg(feature = "async")]
+    pub(crate) fn new(handler: &'s mut (dyn InputHandler + Send + 's)) -> Self {
+        let handler: &'s mut (dyn InputHandler + 's) = handler;
+        Self {
+            handler: NonNull::from(handler),
+            thread: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Lends an input handler, which can only be used on this thread.
+    pub(crate) fn local(handler: &'s mut (dyn InputHandler + 's)) -> Self {
+        Self {
+            handler: NonNull::from(handler),
+            thread: Some(thread::current().id()),
+            marker: PhantomData,
+        }
+    }
+
+    /// Lends the handler on, for as long as this is borrowed.
+    pub(crate) fn reborrow(&mut self) -> Input<'_> {
+        Input {
+            handler: self.handler,
+            thread: self.thread,
+            marker: PhantomData,
+        }
+    }
+
+    /// Whether the handler can be used on this thread.
+    fn usable(&self) -> bool {
+        match self.thread {
+            Some(thread) => thread == thread::current().id(),
+            None => true,
+        }
+    }
+
+    /// Reads user input.
+    pub fn read(&mut self, prompt: &str) -> io::Result<InputResult> {
+        if !self.usable() {
+            return Err(io::Error::other(
+                "input can only be read on the thread running the shell",
+            ));
+        }
+        // SAFETY: Borrowed mutably for `'s`, and usable on this thread.
+        unsafe { self.handler.as_mut() }.read(prompt)
+    }
+
+    /// Gets the lines which have been read, oldest first, if they are
+    /// kept.
+    pub fn history(&self) -> Vec<String> {
+        if !self.usable() {
+            return Vec::new();
+        }
+        // SAFETY: As for `read`.
+        unsafe { self.handler.as_ref() }.history()
+    }
+}
+
 /// Uses simple `std::io` methods to read
 pub struct IO;
 
@@ -49,6 +146,14 @@ where
             Err(e) => Err(convert_rustyline_to_io(e)),
         }
     }
+
+    fn history(&self) -> Vec<String> {
+        let history = rustyline::Editor::history(self);
+        (0..history.len())
+            .filter_map(|i| history.get(i, SearchDirection::Forward).ok()?)
+            .map(|result| result.entry.into_owned())
+            .collect()
+    }
 }
 
 #[cfg(feature = "rustyline")]
@@ -59,3 +164,65 @@ fn convert_rustyline_to_io(e: ReadlineError) -> io::Error {
         e => io::Error::new(io::ErrorKind::Interrupted, e),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::handler::DefaultHandler;
+    use crate::{Command, Shell};
+
+    /// Answers from a list shared with the test, so it isn't `Send`.
+    struct Answers(Rc<RefCell<Vec<String>>>);
+
+    impl InputHandler for Answers {
+        fn read(&mut self, _prompt: &str) -> io::Result<InputResult> {
+            Ok(match self.0.borrow_mut().pop() {
+                Some(answer) => InputResult::S(answer),
+                None => InputResult::EOF,
+            })
+        }
+    }
+
+    fn answers(answers: &[&str]) -> Answers {
+        let answers = answers.iter().map(|answer| answer.to_string());
+        Answers(Rc::new(RefCell::new(answers.collect())))
+    }
+
+    #[test]
+    fn sync_shells_ask_through_handlers_which_arent_send() {
+        let handler = answers(&["y"]);
+        let left = Rc::clone(&handler.0);
+        let mut shell = Shell::new_with_handler(
+            0,
+            "$ ",
+            DefaultHandler::default(),
+            handler,
+        );
+        shell.commands.insert(
+            "count".into(),
+            Command::new("counts.".to_string(), |count: &mut u32, _| {
+                *count += 1;
+                Ok(())
+            })
+            .with_confirmation("Count?".to_string()),
+        );
+        shell.execute("count").assert_success();
+        assert_eq!(shell.state, 1);
+        assert!(left.borrow().is_empty());
+    }
+
+    #[test]
+    fn local_input_is_only_read_on_its_thread() {
+        let mut handler = answers(&["y"]);
+        let mut input = Input::local(&mut handler);
+        thread::scope(|scope| {
+            scope.spawn(|| assert!(input.read("? ").is_err()));
+        });
+        assert!(
+            matches!(input.read("? "), Ok(InputResult::S(answer)) if answer == "y")
+        );
+    }
+}
@@ -1,16 +1,40 @@
 #![cfg_attr(nightly, feature(doc_cfg))]
 
+pub mod builtin;
+pub use builtin::Builtin;
+
 #[doc=include_str!("../README.md")]
 pub mod command;
 pub use command::Command;
+pub mod completion;
+
+pub mod diagnostic;
+pub use diagnostic::Diagnostic;
+pub mod docs;
+
+pub mod context;
+pub use context::Context;
+
+#[cfg(feature = "async")]
+#[cfg_attr(nightly, doc(cfg(feature = "async")))]
+pub mod executor;
 
 pub mod handler;
 #[cfg(feature = "async")]
 pub use handler::AsyncHandler;
 pub use handler::Handler;
+pub mod help;
 pub mod input_handler;
 pub use input_handler::InputHandler;
 
+pub mod middleware;
+pub use middleware::Middleware;
+pub mod mode;
+pub use mode::Mode;
+pub mod namespace;
+pub mod reporter;
+pub use reporter::Reporter;
+
 #[cfg(feature = "app")]
 #[cfg_attr(nightly, doc(cfg(feature = "app")))]
 pub mod app;
@@ -21,9 +45,24 @@ pub use app::App;
 pub mod shell;
 pub use shell::Shell;
 
+pub mod sink;
+
+pub mod testing;
+pub mod theme;
+pub use theme::Theme;
+
+mod suggest;
+
 #[cfg(feature = "clap")]
 #[cfg_attr(nightly, doc(cfg(feature = "clap")))]
 mod clap_command;
 
+#[cfg(feature = "rustyline")]
+#[cfg_attr(nightly, doc(cfg(feature = "rustyline")))]
+pub mod helper;
+#[cfg(feature = "rustyline")]
+#[cfg_attr(nightly, doc(cfg(feature = "rustyline")))]
+pub use helper::ShellfishHelper;
+
 #[cfg(feature = "rustyline")]
 pub use rustyline;
@@ -0,0 +1,70 @@
+//! # Middleware
+//!
+//! Middleware runs around every command in a shell or app, which makes it
+//! a good place for things like timing, logging, metrics and authorization
+//! checks. Middleware is added to
+//! [`Shell::middleware`](crate::Shell::middleware) (or
+//! [`App::middleware`](crate::App::middleware)) and is used by both the
+//! synchronous and asynchronous handlers.
+//!
+//! ```rust
+//! use shellfish::{Middleware, Shell};
+//! use std::error::Error;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! /// Prints how long each command took.
+//! struct Timer;
+//!
+//! impl<T> Middleware<T> for Timer {
+//!     fn after(
+//!         &self,
+//!         _state: &mut T,
+//!         args: &[String],
+//!         _result: &mut Result<(), Box<dyn Error>>,
+//!         elapsed: Duration,
+//!     ) {
+//!         println!("{} took {:?}", args[0], elapsed);
+//!     }
+//! }
+//!
+//! let mut shell = Shell::new((), "[Shell]-$ ");
+//! shell.middleware.push(Arc::new(Timer));
+//! ```
+
+use std::error::Error;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+/// Runs around every command. See the [module level
+/// documentation](crate::middleware) for more.
+///
+/// Middleware is only run around commands, not the built-ins such as `help`.
+pub trait Middleware<T>: Send + Sync {
+    /// Called before a command is run, with its arguments (the first being
+    /// the command name).
+    ///
+    /// Returning [`ControlFlow::Break`] stops the command from being run,
+    /// and the given result is used in its place. The `after` of this and
+    /// any later middleware is then not called.
+    fn before(
+        &self,
+        _state: &mut T,
+        _args: &[String],
+    ) -> ControlFlow<Result<(), Box<dyn Error>>> {
+        ControlFlow::Continue(())
+    }
+
+    /// Called after a command has run, with its result, which may be
+    /// changed, and how long it took to run.
+    ///
+    /// The middleware is called in the reverse order to `before`.
+    fn after(
+        &self,
+        _state: &mut T,
+        _args: &[String],
+        _result: &mut Result<(), Box<dyn Error>>,
+        _elapsed: Duration,
+    ) {
+    }
+}
@@ -0,0 +1,176 @@
+//! # Modes
+//!
+//! Modes let a shell have sub-shells, like the `configure` mode of a
+//! router. A mode has its own prompt and commands, and shares the state
+//! with the rest of the shell. Whilst a mode is active only its commands
+//! (and the built-ins) can be run, and the help only shows them.
+//!
+//! Modes are kept on a stack. A command given the
+//! [`Context`](crate::Context) enters a mode with
+//! [`push_mode`](crate::Context::push_mode) and goes back to the one before
+//! with [`pop_mode`](crate::Context::pop_mode).
+//!
+//! ```rust
+//! use shellfish::{command::Commands, Command, Context, Mode, Shell};
+//! use std::error::Error;
+//!
+//! fn hostname(state: &mut String, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+//!     *state = args.get(1).ok_or("no hostname given")?.clone();
+//!     Ok(())
+//! }
+//!
+//! fn configure(context: &mut Context<'_, '_, String>, _args: Vec<String>) -> Result<(), Box<dyn Error>> {
+//!     let mut commands = Commands::new();
+//!     commands.insert(
+//!         "hostname".into(),
+//!         Command::new("sets the hostname.".to_string(), hostname),
+//!     );
+//!     commands.insert(
+//!         "end".into(),
+//!         Command::new_with_context("leaves configure mode.".to_string(), end),
+//!     );
+//!     context.push_mode(Mode::new("(config)# ".to_string(), commands));
+//!     Ok(())
+//! }
+//!
+//! fn end(context: &mut Context<'_, '_, String>, _args: Vec<String>) -> Result<(), Box<dyn Error>> {
+//!     context.pop_mode();
+//!     Ok(())
+//! }
+//!
+//! let mut shell = Shell::new(String::new(), "# ");
+//! shell.commands.insert(
+//!     "configure".into(),
+//!     Command::new_with_context("enters configure mode.".to_string(), configure),
+//! );
+//! ```
+
+use crate::command::Commands;
+
+/// A mode of a shell, with its own prompt and commands.
+#[derive(Clone)]
+pub struct Mode<'a, T> {
+    /// The prompt shown whilst in this mode.
+    pub prompt: String,
+    /// The commands which can be run in this mode.
+    pub commands: Commands<'a, T>,
+}
+
+impl<'a, T> Mode<'a, T> {
+    /// Creates a new mode.
+    pub fn new(prompt: String, commands: Commands<'a, T>) -> Self {
+        Self { prompt, commands }
+    }
+}
+
+/// A change to the mode stack asked for by a command.
+pub(crate) enum Transition<'a, T> {
+    Push(Mode<'a, T>),
+    Pop,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::Mode;
+    use crate::command::Commands;
+    use crate::{testing, Command, Context};
+
+    fn configure(
+        context: &mut Context<'_, '_, String>,
+        _args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut commands = Commands::new();
+        commands.insert(
+            "hostname".into(),
+            Command::new("sets the hostname.".to_string(), |state, args| {
+                *state = args[1].clone();
+                Ok(())
+            }),
+        );
+        commands.insert(
+            "end".into(),
+            Command::new_with_context(
+                "leaves configure mode.".to_string(),
+                end,
+            ),
+        );
+        context.push_mode(Mode::new("(config)# ".to_string(), commands));
+        Ok(())
+    }
+
+    fn end(
+        context: &mut Context<'_, '_, String>,
+        _args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        context.pop_mode();
+        Ok(())
+    }
+
+    fn shell() -> crate::Shell<
+        'static,
+        String,
+        &'static str,
+        crate::handler::DefaultHandler,
+        testing::Script,
+    > {
+        let mut shell = testing::shell(String::new());
+        shell.commands.insert(
+            "configure".into(),
+            Command::new_with_context(
+                "enters configure mode.".to_string(),
+                configure,
+            ),
+        );
+        shell.commands.insert(
+            "end".into(),
+            Command::new_with_context("leaves a mode.".to_string(), end),
+        );
+        shell
+    }
+
+    #[test]
+    fn runs_only_the_modes_commands() {
+        let mut shell = shell();
+        shell.execute("configure").assert_success();
+        assert_eq!(shell.modes.len(), 1);
+
+        let help = shell.execute("help");
+        help.assert_stdout_contains("hostname")
+            .assert_stdout_contains("leaves configure mode.");
+        assert!(!help.output.contains("enters configure mode."));
+
+        shell
+            .execute("configure")
+            .assert_failure()
+            .assert_stderr_contains("not found: configure");
+        shell.execute("hostname router").assert_success();
+        assert_eq!(shell.state, "router");
+    }
+
+    #[test]
+    fn popping_restores_the_shells_commands() {
+        let mut shell = shell();
+        shell.execute("configure");
+        shell.execute("end").assert_success();
+        assert!(shell.modes.is_empty());
+
+        shell
+            .execute("help")
+            .assert_stdout_contains("enters configure mode.");
+        shell
+            .execute("hostname router")
+            .assert_stderr_contains("not found: hostname");
+        shell.execute("configure").assert_success();
+    }
+
+    #[test]
+    fn popping_without_a_mode_does_nothing() {
+        let mut shell = shell();
+        shell.execute("end").assert_success();
+        assert!(shell.modes.is_empty());
+        shell.execute("configure").assert_success();
+        assert_eq!(shell.modes.len(), 1);
+    }
+}
@@ -0,0 +1,535 @@
+//! # Namespaces
+//!
+//! A namespace mounts one set of commands within another under a prefix,
+//! so that packs of commands can be written once and shared between
+//! shells. A `db` namespace with a `query` command is run as `db query`.
+//!
+//! The mounted commands have their own state, which is found from the
+//! state of the shell they are mounted in with a pair of projections. The
+//! help, completion and running of commands all look into namespaces, and
+//! running a namespace on its own lists its commands.
+//!
+//! ```rust
+//! use shellfish::{command::Commands, namespace::Namespace, Command, Shell};
+//! use std::error::Error;
+//!
+//! #[derive(Default)]
+//! struct Database {
+//!     queries: usize,
+//! }
+//!
+//! #[derive(Default)]
+//! struct State {
+//!     db: Database,
+//! }
+//!
+//! fn query(state: &mut Database, _args: Vec<String>) -> Result<(), Box<dyn Error>> {
+//!     state.queries += 1;
+//!     Ok(())
+//! }
+//!
+//! // A pack of database commands, which knows nothing of `State`
+//! let mut db = Commands::new();
+//! db.insert(
+//!     "query".into(),
+//!     Command::new("runs a query.".to_string(), query),
+//! );
+//!
+//! let mut shell = Shell::new(State::default(), "[Shell]-$");
+//! shell.commands.insert(
+//!     "db".into(),
+//!     Command::new_namespace(
+//!         "database tools.".to_string(),
+//!         Namespace::new(db, |state: &State| &state.db, |state| &mut state.db),
+//!     ),
+//! );
+//! ```
+//!
+//! Commands within a namespace which are given the
+//! [`Context`](crate::Context) see the namespace's commands, and so can
+//! change them. They can't change the modes of the shell.
+
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
+
+use crate::builtin::Builtins;
+use crate::command::Commands;
+#[cfg(feature = "rustyline")]
+use crate::completion::Completion;
+use crate::handler::{self, Failure};
+use crate::help::{self, Help, HelpEntry};
+#[cfg(feature = "rustyline")]
+use crate::suggest::{self, Abbreviation};
+use crate::Context;
+
+/// Commands mounted within another shell's commands, see the
+/// [module level documentation](self).
+///
+/// A namespace is added with
+/// [`Command::new_namespace`](crate::Command::new_namespace).
+pub struct Namespace<T>(Box<dyn Mount<T>>);
+
+impl<T> Namespace<T> {
+    /// Creates a new namespace from some commands, and the functions which
+    /// find their state from the state of the shell they are mounted in.
+    pub fn new<U: Send + 'static>(
+        commands: Commands<'static, U>,
+        project: fn(&T) -> &U,
+        project_mut: fn(&mut T) -> &mut U,
+    ) -> Self
+    where
+        T: Send + 'static,
+    {
+        Self(Box::new(Mounted {
+            commands,
+            project,
+            project_mut,
+        }))
+    }
+
+    /// Gets the help for the commands within this namespace.
+    pub(crate) fn help(&self, state: &T) -> Vec<HelpEntry> {
+        self.0.help(state)
+    }
+
+    /// Gets the entries of every command within this namespace, for
+    /// reference documentation.
+    pub(crate) fn reference(&self) -> Vec<HelpEntry> {
+        self.0.reference()
+    }
+
+    /// Completes a line within this namespace, where `args` are those
+    /// after the namespace's name, which may be abbreviated if
+    /// `abbreviations` is set.
+    #[cfg(feature = "rustyline")]
+    pub(crate) fn complete(
+        &self,
+        state: &T,
+        args: &[String],
+        partial: &str,
+        abbreviations: bool,
+    ) -> Vec<Completion> {
+        self.0.complete(state, args, partial, abbreviations)
+    }
+
+    /// Checks there is a command within this namespace which can be run,
+    /// reporting why if there isn't one, and expands its name if it is
+    /// abbreviated. `args` are those after the namespace's name, and
+    /// mustn't be empty.
+    pub(crate) fn find(
+        &mut self,
+        prefix: &str,
+        context: &mut Context<'_, '_, T>,
+        args: &mut [String],
+        threshold: usize,
+    ) -> bool {
+        self.0.find(prefix, context, args, threshold)
+    }
+
+    /// Runs a line within this namespace, where `args` are those after the
+    /// namespace's name. A failure is given back to be reported once the
+    /// middleware around the namespace have seen it.
+    pub(crate) fn dispatch(
+        &mut self,
+        prefix: &str,
+        context: &mut Context<'_, '_, T>,
+        args: Vec<String>,
+        threshold: usize,
+    ) -> Result<(), Failure> {
+        self.0.dispatch(prefix, context, args, threshold)
+    }
+
+    /// Runs a line within this namespace asynchronously, where `args` are
+    /// those after the namespace's name.
+    #[cfg(feature = "async")]
+    pub(crate) fn dispatch_async<'f>(
+        &'f mut self,
+        prefix: &'f str,
+        context: &'f mut Context<'_, '_, T>,
+        args: Vec<String>,
+        threshold: usize,
+    ) -> Dispatch<'f> {
+        self.0.dispatch_async(prefix, context, args, threshold)
+    }
+}
+
+impl<T> Clone for Namespace<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.box_clone())
+    }
+}
+
+/// Running a line within a namespace asynchronously.
+#[cfg(feature = "async")]
+type Dispatch<'f> =
+    Pin<Box<dyn Future<Output = Result<(), Failure>> + Send + 'f>>;
+
+/// A namespace with the type of its state hidden.
+trait Mount<T>: Send + Sync {
+    fn box_clone(&self) -> Box<dyn Mount<T>>;
+
+    fn help(&self, state: &T) -> Vec<HelpEntry>;
+
+    fn reference(&self) -> Vec<HelpEntry>;
+
+    #[cfg(feature = "rustyline")]
+    fn complete(
+        &self,
+        state: &T,
+        args: &[String],
+        partial: &str,
+        abbreviations: bool,
+    ) -> Vec<Completion>;
+
+    fn find(
+        &mut self,
+        prefix: &str,
+        context: &mut Context<'_, '_, T>,
+        args: &mut [String],
+        threshold: usize,
+    ) -> bool;
+
+    fn dispatch(
+        &mut self,
+        prefix: &str,
+        context: &mut Context<'_, '_, T>,
+        args: Vec<String>,
+        threshold: usize,
+    ) -> Result<(), Failure>;
+
+    #[cfg(feature = "async")]
+    fn dispatch_async<'f>(
+        &'f mut self,
+        prefix: &'f str,
+        context: &'f mut Context<'_, '_, T>,
+        args: Vec<String>,
+        threshold: usize,
+    ) -> Dispatch<'f>;
+}
+
+/// Commands with a state of `U`, mounted in a shell with a state of `T`.
+struct Mounted<T, U> {
+    commands: Commands<'static, U>,
+    project: fn(&T) -> &U,
+    project_mut: fn(&mut T) -> &mut U,
+}
+
+impl<T, U> Mounted<T, U> {
+    /// Creates the context the mounted commands are run in.
+    /// Built ins can't be run within namespaces, so it is given none.
+    fn context<'s>(
+        &'s mut self,
+        context: &'s mut Context<'_, '_, T>,
+        builtins: &'s Builtins<'static>,
+    ) -> Context<'s, 'static, U> {
+        Context {
+            builtins,
+            commands: &mut self.commands,
+            state: (self.project_mut)(context.state),
+            description: context.description,
+            middleware: &[],
+            line: context.line,
+            output: &mut *context.output,
+            error: &mut *context.error,
+            input_handler: context.input_handler.reborrow(),
+            batch: context.batch,
+            abbreviations: context.abbreviations,
+            reporter: context.reporter,
+            theme: context.theme,
+            error_theme: context.error_theme,
+            width: context.width,
+            #[cfg(feature = "async")]
+            executor: context.executor,
+            transitions: Vec::new(),
+            failed: false,
+        }
+    }
+}
+
+impl<T: Send + 'static, U: Send + 'static> Mount<T> for Mounted<T, U> {
+    fn box_clone(&self) -> Box<dyn Mount<T>> {
+        Box::new(Mounted {
+            commands: self.commands.clone(),
+            project: self.project,
+            project_mut: self.project_mut,
+        })
+    }
+
+    fn help(&self, state: &T) -> Vec<HelpEntry> {
+        help::help_entries(&self.commands, (self.project)(state))
+    }
+
+    fn reference(&self) -> Vec<HelpEntry> {
+        help::reference_entries(&self.commands)
+    }
+
+    #[cfg(feature = "rustyline")]
+    fn complete(
+        &self,
+        state: &T,
+        args: &[String],
+        partial: &str,
+        abbreviations: bool,
+    ) -> Vec<Completion> {
+        let state = (self.project)(state);
+        match args.first() {
+            // Complete the name of a command
+            None => self
+                .commands
+                .iter()
+                .filter(|(name, _)| name.starts_with(partial))
+                .filter(|(_, command)| command.available(state).is_ok())
+                .map(|(name, command)| {
+                    Completion::with_description(&**name, &*command.help)
+                })
+                .collect(),
+            Some(name) => {
+                let names = self.commands.keys().map(|name| &**name);
+                let name = match abbreviations {
+                    true => match suggest::expand(name, names) {
+                        Abbreviation::Unique(full) => full,
+                        _ => name,
+                    },
+                    false => name,
+                };
+                match self.commands.get(name) {
+                    Some(command) if command.available(state).is_ok() => {
+                        command.complete(state, args, partial, abbreviations)
+                    }
+                    _ => Vec::new(),
+                }
+            }
+        }
+    }
+
+    fn find(
+        &mut self,
+        prefix: &str,
+        context: &mut Context<'_, '_, T>,
+        args: &mut [String],
+        threshold: usize,
+    ) -> bool {
+        let builtins = Builtins::new();
+        let mut context = self.context(context, &builtins);
+        handler::find(prefix, args, &mut context, threshold)
+    }
+
+    fn dispatch(
+        &mut self,
+        prefix: &str,
+        context: &mut Context<'_, '_, T>,
+        args: Vec<String>,
+        threshold: usize,
+    ) -> Result<(), Failure> {
+        let builtins = Builtins::new();
+        let mut context = self.context(context, &builtins);
+        if args.is_empty() {
+            print_help(prefix, &mut context);
+            Ok(())
+        } else {
+            handler::run(prefix, args, &mut context, threshold)
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn dispatch_async<'f>(
+        &'f mut self,
+        prefix: &'f str,
+        context: &'f mut Context<'_, '_, T>,
+        args: Vec<String>,
+        threshold: usize,
+    ) -> Dispatch<'f> {
+        Box::pin(async move {
+            let builtins = Builtins::new();
+            let mut context = self.context(context, &builtins);
+            if args.is_empty() {
+                print_help(prefix, &mut context);
+                Ok(())
+            } else {
+                handler::run_async(prefix, args, &mut context, threshold).await
+            }
+        })
+    }
+}
+
+/// Lists the commands of a namespace, when it is run on its own.
+fn print_help<U>(prefix: &str, context: &mut Context<'_, '_, U>) {
+    let mut entries = help::help_entries(context.commands, context.state);
+    for entry in &mut entries {
+        entry.name.insert_str(0, prefix);
+    }
+    let _ = Help::new(context.width)
+        .with_entries(None, entries)
+        .render(context.output, context.theme);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::ops::ControlFlow;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::Namespace;
+    use crate::command::Commands;
+    use crate::{testing, Command, Context, Diagnostic, Handler, Middleware};
+
+    #[derive(Default)]
+    struct State {
+        log: Vec<String>,
+        db: bool,
+    }
+
+    /// Logs what it sees of each command.
+    struct Log;
+
+    impl Middleware<State> for Log {
+        fn before(
+            &self,
+            state: &mut State,
+            args: &[String],
+        ) -> ControlFlow<Result<(), Box<dyn Error>>> {
+            state.log.push(format!("before {}", args.join(" ")));
+            ControlFlow::Continue(())
+        }
+
+        fn after(
+            &self,
+            state: &mut State,
+            args: &[String],
+            result: &mut Result<(), Box<dyn Error>>,
+            _elapsed: Duration,
+        ) {
+            let outcome = match result {
+                Ok(()) => "ok",
+                Err(e) if e.is::<Diagnostic>() => "failed with a diagnostic",
+                Err(_) => "failed",
+            };
+            state
+                .log
+                .push(format!("after {} {}", args.join(" "), outcome));
+        }
+    }
+
+    fn shell() -> crate::Shell<
+        'static,
+        State,
+        &'static str,
+        crate::handler::DefaultHandler,
+        testing::Script,
+    > {
+        let mut db = Commands::new();
+        db.insert(
+            "query".into(),
+            Command::new("runs a query.".to_string(), |_: &mut bool, _| {
+                Err(Diagnostic::new("no such table".to_string()).into())
+            }),
+        );
+        db.insert(
+            "drop".into(),
+            Command::new("drops a table.".to_string(), |_, _| Ok(()))
+                .with_guard(|connected| match connected {
+                    true => Ok(()),
+                    false => Err("not connected".to_string()),
+                }),
+        );
+
+        let mut shell = testing::shell(State::default());
+        shell.middleware.push(Arc::new(Log));
+        shell.commands.insert(
+            "db".into(),
+            Command::new_namespace(
+                "database tools.".to_string(),
+                Namespace::new(
+                    db,
+                    |state: &State| &state.db,
+                    |state| &mut state.db,
+                ),
+            ),
+        );
+        shell
+    }
+
+    #[test]
+    fn middleware_sees_failures_within_namespaces() {
+        let mut shell = shell();
+        let outcome = shell.execute("db query");
+        assert_eq!(outcome.error.matches("no such table").count(), 1);
+        assert_eq!(
+            shell.state.log,
+            ["before db query", "after db query failed with a diagnostic"]
+        );
+    }
+
+    #[test]
+    fn expands_abbreviations_within_namespaces() {
+        let mut shell = shell();
+        shell
+            .execute("db q")
+            .assert_stderr_contains("not found: db q");
+        shell.abbreviations = true;
+        shell.execute("d q").assert_stderr_contains("no such table");
+        for line in ["\"\" x", "db \"\" x"] {
+            let outcome = shell.execute(line);
+            outcome.assert_stderr_contains("not found");
+            assert!(!outcome.error.contains("Ambiguous"), "{}", outcome.error);
+        }
+        assert_eq!(
+            shell.state.log,
+            ["before db query", "after db query failed with a diagnostic"]
+        );
+    }
+
+    /// Handles `log` itself, and leaves the rest to the context.
+    struct Custom;
+
+    impl Handler<State> for Custom {
+        fn handle(
+            &self,
+            line: Vec<String>,
+            context: &mut Context<'_, '_, State>,
+        ) -> bool {
+            if line.first().map(String::as_str) == Some("log") {
+                let log = context.state.log.join(", ");
+                let _ = writeln!(context.output, "{}", log);
+                return false;
+            }
+            let _ = context.dispatch(line, 2);
+            false
+        }
+    }
+
+    #[test]
+    fn custom_handlers_run_commands_within_namespaces() {
+        let commands = shell().commands;
+        let mut shell = crate::Shell::new_with_handler(
+            State::default(),
+            "$ ",
+            Custom,
+            testing::Script::default(),
+        );
+        shell.commands = commands;
+        shell.middleware.push(Arc::new(Log));
+        shell
+            .execute("db query")
+            .assert_stderr_contains("no such table");
+        shell
+            .execute("db qurey")
+            .assert_stderr_contains("did you mean");
+        shell
+            .execute("log")
+            .assert_stdout_contains("after db query failed with a diagnostic");
+    }
+
+    #[test]
+    fn middleware_skips_commands_which_cant_be_run() {
+        let mut shell = shell();
+        shell
+            .execute("db drop")
+            .assert_stderr_contains("not connected");
+        shell
+            .execute("db missing")
+            .assert_stderr_contains("db missing");
+        assert!(shell.state.log.is_empty(), "{:?}", shell.state.log);
+    }
+}
@@ -0,0 +1,289 @@
+//! # Reporter
+//!
+//! A reporter is told about everything which goes wrong whilst running a
+//! line, such as a command failing or not being found, and decides how to
+//! show it to the user. Each method is given where errors should be
+//! written, which is the shell's [`error`](crate::Shell::error) unless the
+//! output is being captured, and the [`Theme`] to write them with. The
+//! default reporter writes in the theme's error colour, but reporters can also
+//! log, or look into the error for hints.
+//!
+//! Errors which are [`Diagnostic`]s are shown with the line they came from
+//! and any help, see the [`diagnostic`](crate::diagnostic) module.
+//!
+//! Every method has a default, so a reporter only needs to implement those
+//! it wants to change.
+//!
+//! ```rust
+//! use shellfish::{Reporter, Shell, Theme};
+//! use std::error::Error;
+//! use std::io::{self, Write};
+//! use std::sync::Arc;
+//!
+//! /// Logs failures rather than printing them
+//! struct Logger;
+//!
+//! impl Reporter for Logger {
+//!     fn command_failed(
+//!         &self,
+//!         out: &mut dyn Write,
+//!         _theme: &Theme,
+//!         _line: &str,
+//!         name: &str,
+//!         args: &[String],
+//!         error: &(dyn Error + 'static),
+//!     ) -> io::Result<()> {
+//!         // Pretend this is a log
+//!         writeln!(out, "[error] {} {:?}: {}", name, args, error)
+//!     }
+//! }
+//!
+//! let mut shell = Shell::new((), "[Shell]-$");
+//! shell.reporter = Arc::new(Logger);
+//! ```
+
+use std::error::Error;
+use std::io::{self, Write};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::diagnostic::Diagnostic;
+use crate::shell::{lex, unescape, Lexeme, UnescapeError};
+use crate::Theme;
+
+/// Reports what goes wrong whilst running a line. See the
+/// [module level documentation](self).
+pub trait Reporter: Send + Sync {
+    /// A command returned an error. `line` is the line it was run from,
+    /// `name` is the full name of the command (including any namespaces)
+    /// and `args` are the arguments after it.
+    fn command_failed(
+        &self,
+        out: &mut dyn Write,
+        theme: &Theme,
+        line: &str,
+        name: &str,
+        args: &[String],
+        error: &(dyn Error + 'static),
+    ) -> io::Result<()> {
+        if let Some(diagnostic) = error.downcast_ref::<Diagnostic>() {
+            return write_diagnostic(out, theme, line, name, args, diagnostic);
+        }
+
+        writeln!(
+            out,
+            "{} {}",
+            theme.error.paint("Command exited unsuccessfully:"),
+            name
+        )?;
+        writeln!(out, "{}", theme.error.paint(error))?;
+
+        // Print what caused it
+        let mut source = error.source();
+        while let Some(error) = source {
+            writeln!(out, "{} {}", theme.error.paint("caused by:"), error)?;
+            source = error.source();
+        }
+        Ok(())
+    }
+
+    /// No command could be found with the given name. `suggestion` is the
+    /// closest command, if there is one close enough.
+    fn not_found(
+        &self,
+        out: &mut dyn Write,
+        theme: &Theme,
+        name: &str,
+        args: &[String],
+        suggestion: Option<&str>,
+    ) -> io::Result<()> {
+        let _ = args;
+        writeln!(out, "{} {}", theme.error.paint("Command not found:"), name)?;
+        if let Some(suggestion) = suggestion {
+            writeln!(out, "did you mean `{}`?", suggestion)?;
+        }
+        Ok(())
+    }
+
+    /// A command's guard stopped it from being run.
+    fn unavailable(
+        &self,
+        out: &mut dyn Write,
+        theme: &Theme,
+        name: &str,
+        args: &[String],
+        reason: &str,
+    ) -> io::Result<()> {
+        let _ = args;
+        writeln!(
+            out,
+            "{} {} ({})",
+            theme.error.paint("Command unavailable:"),
+            name,
+            reason
+        )
+    }
+
+    /// An abbreviated command name could be more than one command.
+    fn ambiguous(
+        &self,
+        out: &mut dyn Write,
+        theme: &Theme,
+        name: &str,
+        candidates: &[&str],
+    ) -> io::Result<()> {
+        writeln!(out, "{} {}", theme.error.paint("Ambiguous command:"), name)?;
+        writeln!(out, "could be `{}`", candidates.join("`, `"))
+    }
+
+    /// A line couldn't be split into arguments.
+    fn invalid_line(
+        &self,
+        out: &mut dyn Write,
+        theme: &Theme,
+        line: &str,
+        error: &UnescapeError,
+    ) -> io::Result<()> {
+        let _ = line;
+        writeln!(out, "{}", theme.error.paint(error))
+    }
+}
+
+/// Shellfish's default reporter, which prints everything to the standard
+/// error.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DefaultReporter;
+
+impl Reporter for DefaultReporter {}
+
+/// Writes a diagnostic, with a caret under the argument it points to.
+fn write_diagnostic(
+    out: &mut dyn Write,
+    theme: &Theme,
+    line: &str,
+    name: &str,
+    args: &[String],
+    diagnostic: &Diagnostic,
+) -> io::Result<()> {
+    writeln!(out, "{} {}", theme.error_label.paint("error:"), diagnostic)?;
+
+    if let Some(arg) = diagnostic.arg {
+        let (offset, width) = span(line, name, args, arg);
+        writeln!(out, "{} {}", theme.muted.paint("  |"), line)?;
+        writeln!(
+            out,
+            "{} {}{}",
+            theme.muted.paint("  |"),
+            " ".repeat(offset),
+            theme.error.paint("^".repeat(width.max(1)))
+        )?;
+    }
+
+    if let Some(help) = &diagnostic.help {
+        writeln!(out, "{} {}", theme.heading.paint("help:"), help)?;
+    }
+    if let Some(suggestion) = &diagnostic.suggestion {
+        writeln!(out, "did you mean `{}`?", suggestion)?;
+    }
+    Ok(())
+}
+
+/// Finds the columns an argument of a command takes up within the line it
+/// was run from, as where they start and how many there are. `name` is the
+/// full name of the command, which says how many namespaces come first.
+fn span(line: &str, name: &str, args: &[String], arg: usize) -> (usize, usize) {
+    // A missing argument is pointed to just after the line
+    let after = (line.width() + 1, 1);
+    if arg > args.len() {
+        return after;
+    }
+
+    // Confirmation takes out the first `--yes`, which is still in the line
+    let first = name.matches(' ').count() + 1;
+    let mut index = first - 1 + arg;
+    let (pieces, _) = lex(line);
+    let count = pieces.last().map_or(0, |piece| piece.argument + 1);
+    if count > first + args.len() {
+        let yes = unescape(line).ok().and_then(|words| {
+            words.iter().skip(first).position(|word| word == "--yes")
+        });
+        if yes.is_some_and(|yes| yes < arg) {
+            index += 1;
+        }
+    }
+
+    let mut spans = pieces
+        .iter()
+        .filter(|piece| piece.argument == index)
+        .filter(|piece| piece.lexeme != Lexeme::Separator)
+        .map(|piece| piece.span.clone());
+    let start = match spans.next() {
+        Some(span) => span,
+        None => return after,
+    };
+    let end = spans.next_back().map_or(start.end, |span| span.end);
+    (line[..start.start].width(), line[start.start..end].width())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::span;
+    use crate::testing;
+    use crate::theme::ColourChoice;
+    use crate::{Command, Diagnostic};
+
+    fn shell() -> crate::Shell<
+        'static,
+        (),
+        &'static str,
+        crate::handler::DefaultHandler,
+        testing::Script,
+    > {
+        let mut shell = testing::shell(());
+        shell.commands.insert(
+            "connect".into(),
+            Command::new("connects.".to_string(), |_, _| {
+                Err(Diagnostic::new("invalid port".to_string()).at(2).into())
+            }),
+        );
+        shell
+    }
+
+    #[test]
+    fn plain_themes_write_no_colours() {
+        let mut shell = shell();
+        shell.theme.colour = ColourChoice::Never;
+        let outcome = shell.execute("connect localhost 9999999");
+        assert!(outcome.error.starts_with("error: invalid port"));
+        assert!(!outcome.error.contains('\x1b'), "{:?}", outcome.error);
+    }
+
+    #[test]
+    fn points_into_the_line_as_it_was_entered() {
+        let mut shell = shell();
+        shell.abbreviations = true;
+        let outcome = shell.execute("con \"local host\" 日本語");
+        let lines: Vec<&str> = outcome.error.lines().collect();
+        assert_eq!(lines[1], "  | con \"local host\" 日本語");
+        assert_eq!(lines[2], format!("  | {}^^^^^^", " ".repeat(17)));
+    }
+
+    #[test]
+    fn points_past_namespaces_and_confirmations() {
+        let args = ["a".to_string(), "b".to_string()];
+        assert_eq!(span("db q a b", "db query", &args, 0), (3, 1));
+        assert_eq!(span("db q a b", "db query", &args, 2), (7, 1));
+        assert_eq!(span("db q --yes a b", "db query", &args, 1), (11, 1));
+        assert_eq!(span("db q a --yes b", "db query", &args, 1), (5, 1));
+        assert_eq!(span("db q a --yes b", "db query", &args, 2), (13, 1));
+        assert_eq!(span("db q a b", "db query", &args, 3), (9, 1));
+    }
+
+    #[test]
+    fn coloured_themes_write_colours() {
+        let mut shell = shell();
+        shell.theme.colour = ColourChoice::Always;
+        let outcome = shell.execute("connect localhost 9999999");
+        assert!(outcome.error.contains('\x1b'));
+    }
+}
@@ -1,14 +1,24 @@
 //use std::collections::HashMap;
 use indexmap::IndexMap;
 use std::fmt::Display;
-use std::io;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::sync::Arc;
 
 #[cfg(feature = "rustyline")]
 use thiserror::Error;
-use yansi::Paint;
 
+#[cfg(feature = "async")]
+use crate::executor::{BlockOn, Executor};
 use crate::{
-    input_handler::{InputResult, IO},
+    builtin::Builtins,
+    command::Commands,
+    help::DEFAULT_WIDTH,
+    input_handler::{Input, InputResult, IO},
+    mode::Transition,
+    reporter::DefaultReporter,
+    sink::Sink,
+    suggest::Abbreviation,
     *,
 };
 
@@ -30,7 +40,10 @@ pub struct Shell<'a, T, M: Display, H, I: InputHandler> {
     /// This is a list of commands for the shell. The hashmap key is the
     /// name of the command (ie `"greet"`) and the value is a wrapper
     /// to the function it corresponds to (as well as help information.)
-    pub commands: IndexMap<&'a str, Command<T>>,
+    pub commands: Commands<'a, T>,
+    /// The commands built in to the handler, such as `help`. See the
+    /// [`builtin`](crate::builtin) module for more.
+    pub builtins: Builtins<'a>,
     /// This is the state of the shell. This stores any values that you
     /// need to be persisted over multiple shell commands. For example
     /// it may be a simple counter or maybe a session ID.
@@ -43,6 +56,46 @@ pub struct Shell<'a, T, M: Display, H, I: InputHandler> {
     pub description: String,
     /// The input method
     pub input_handler: I,
+    /// Middleware which is run around every command. See the
+    /// [`middleware`](crate::middleware) module for more.
+    pub middleware: Vec<Arc<dyn Middleware<T>>>,
+    /// Whether commands can be abbreviated. When turned on any prefix of a
+    /// command name which is not shared with another command (for example
+    /// `gr` for `greet`) runs that command. Ambiguous prefixes list the
+    /// commands they could be. This applies within namespaces too, so
+    /// `db q` runs `db query`.
+    ///
+    /// This is off by default.
+    pub abbreviations: bool,
+    /// Whether the shell is being run non-interactively, for example from
+    /// a script. In batch mode commands are not asked to be confirmed.
+    ///
+    /// This is off by default.
+    pub batch: bool,
+    /// Where the output of built ins and commands goes. This is the
+    /// standard output by default. See the [`sink`](crate::sink) module
+    /// for more.
+    pub output: Sink,
+    /// Where errors go. This is the standard error by default.
+    pub error: Sink,
+    /// What is told about anything which goes wrong, such as a command
+    /// failing. See the [`reporter`](crate::reporter) module for more.
+    pub reporter: Arc<dyn Reporter>,
+    /// The colours used, which by default are only used on terminals.
+    /// See the [`theme`](crate::theme) module for more.
+    pub theme: Theme,
+    /// Runs asynchronous commands when the shell is run synchronously.
+    /// See the [`executor`](crate::executor) module for more.
+    #[cfg(feature = "async")]
+    #[cfg_attr(nightly, doc(cfg(feature = "async")))]
+    pub executor: Arc<dyn Executor>,
+    /// The modes which have been entered, the last being the active one.
+    /// Whilst a mode is active its prompt and commands are used instead of
+    /// the shell's. See the [`mode`](crate::mode) module for more.
+    pub modes: Vec<Mode<'a, T>>,
+    /// Lends the commands to any [`ShellfishHelper`]s whilst reading.
+    #[cfg(feature = "rustyline")]
+    pub(crate) attachment: helper::Attachment<T>,
 }
 
 impl<'a, T, M: Display> Shell<'a, T, M, handler::DefaultHandler, IO> {
@@ -54,10 +107,23 @@ impl<'a, T, M: Display> Shell<'a, T, M, handler::DefaultHandler, IO> {
         Shell {
             prompt,
             commands: IndexMap::new(),
+            builtins: builtin::defaults(),
             state,
-            handler: handler::DefaultHandler(),
+            handler: handler::DefaultHandler::default(),
             description: String::new(),
             input_handler: IO,
+            abbreviations: false,
+            batch: false,
+            output: Sink::stdout(),
+            error: Sink::stderr(),
+            reporter: Arc::new(DefaultReporter),
+            theme: Theme::default(),
+            #[cfg(feature = "async")]
+            executor: Arc::new(BlockOn),
+            modes: Vec::new(),
+            middleware: Vec::new(),
+            #[cfg(feature = "rustyline")]
+            attachment: helper::Attachment::new(),
         }
     }
 }
@@ -73,10 +139,23 @@ impl<'a, T, M: Display> Shell<'a, T, M, handler::DefaultAsyncHandler, IO> {
         Shell {
             prompt,
             commands: IndexMap::new(),
+            builtins: builtin::defaults(),
             state,
-            handler: handler::DefaultAsyncHandler(),
+            handler: handler::DefaultAsyncHandler::default(),
             description: String::new(),
             input_handler: IO,
+            abbreviations: false,
+            batch: false,
+            output: Sink::stdout(),
+            error: Sink::stderr(),
+            reporter: Arc::new(DefaultReporter),
+            theme: Theme::default(),
+            #[cfg(feature = "async")]
+            executor: Arc::new(BlockOn),
+            modes: Vec::new(),
+            middleware: Vec::new(),
+            #[cfg(feature = "rustyline")]
+            attachment: helper::Attachment::new(),
         }
     }
 }
@@ -92,10 +171,23 @@ impl<'a, T, M: Display, H: Handler<T>, I: InputHandler> Shell<'a, T, M, H, I> {
         Shell {
             prompt,
             commands: IndexMap::new(),
+            builtins: builtin::defaults(),
             state,
             handler,
             description: String::new(),
             input_handler,
+            abbreviations: false,
+            batch: false,
+            output: Sink::stdout(),
+            error: Sink::stderr(),
+            reporter: Arc::new(DefaultReporter),
+            theme: Theme::default(),
+            #[cfg(feature = "async")]
+            executor: Arc::new(BlockOn),
+            modes: Vec::new(),
+            middleware: Vec::new(),
+            #[cfg(feature = "rustyline")]
+            attachment: helper::Attachment::new(),
         }
     }
 
@@ -103,30 +195,110 @@ impl<'a, T, M: Display, H: Handler<T>, I: InputHandler> Shell<'a, T, M, H, I> {
     pub fn run(&mut self) -> io::Result<()> {
         '_shell: loop {
             // Read a line
-            let line =
-                match self.input_handler.read(&self.prompt.to_string())? {
-                    InputResult::S(line) => line,
-                    InputResult::Interrupted => continue '_shell,
-                    InputResult::EOF => break '_shell,
-                };
+            let line = match self.read_line()? {
+                InputResult::S(line) => line,
+                InputResult::Interrupted => continue '_shell,
+                InputResult::EOF => break '_shell,
+            };
 
             // Runs the line
-            match Self::unescape(line.trim()) {
-                Ok(line) => {
-                    if self.handler.handle(
-                        line,
-                        &self.commands,
-                        &mut self.state,
-                        &self.description,
-                    ) {
-                        break '_shell;
-                    }
-                }
-                Err(e) => eprintln!("{}", Paint::red(e.to_string().as_str())),
+            let (mut output, mut error) =
+                (self.output.clone(), self.error.clone());
+            let theme = self.theme.resolve(self.output.is_terminal());
+            let error_theme = self.theme.resolve(self.error.is_terminal());
+            let width = self.output.width();
+            let (quit, _) = self.run_line(
+                &line,
+                &mut output,
+                &mut error,
+                &theme,
+                &error_theme,
+                width,
+            );
+            let _ = self.output.flush();
+            let _ = self.error.flush();
+            if quit {
+                break '_shell;
             }
         }
         Ok(())
     }
+
+    /// Runs a single line as if it had been entered, without reading from
+    /// the input handler, and returns what it wrote. Questions, such as
+    /// confirmations, are still asked through the input handler unless
+    /// the shell is in [`batch`](Self::batch) mode.
+    ///
+    /// As the output isn't a terminal, colours are only used if the
+    /// [`theme`](Self::theme) always uses them, and help is wrapped to
+    /// [`DEFAULT_WIDTH`] columns.
+    pub fn execute(&mut self, line: &str) -> Outcome {
+        let (mut output, mut error) = (Vec::new(), Vec::new());
+        let theme = self.theme.resolve(false);
+        let (quit, success) = self.run_line(
+            line,
+            &mut output,
+            &mut error,
+            &theme,
+            &theme,
+            DEFAULT_WIDTH,
+        );
+        Outcome::new(quit, success, output, error)
+    }
+
+    /// Runs a line, returning whether the shell should quit and whether the
+    /// line succeeded.
+    fn run_line(
+        &mut self,
+        line: &str,
+        output: &mut (dyn Write + Send),
+        error: &mut (dyn Write + Send),
+        theme: &Theme,
+        error_theme: &Theme,
+        width: usize,
+    ) -> (bool, bool) {
+        let mut args = match unescape(line.trim()) {
+            Ok(args) => args,
+            Err(e) => {
+                let _ =
+                    self.reporter.invalid_line(error, error_theme, line, &e);
+                return (false, false);
+            }
+        };
+        if !self.expand_abbreviation(&mut args, error, error_theme) {
+            return (false, false);
+        }
+
+        let commands = match self.modes.last_mut() {
+            Some(mode) => &mut mode.commands,
+            None => &mut self.commands,
+        };
+        let mut context = Context {
+            builtins: &self.builtins,
+            commands,
+            state: &mut self.state,
+            description: &self.description,
+            middleware: &self.middleware,
+            line: line.trim(),
+            output,
+            error,
+            input_handler: Input::local(&mut self.input_handler),
+            batch: self.batch,
+            abbreviations: self.abbreviations,
+            reporter: &*self.reporter,
+            theme,
+            error_theme,
+            width,
+            #[cfg(feature = "async")]
+            executor: &*self.executor,
+            transitions: Vec::new(),
+            failed: false,
+        };
+        let quit = self.handler.handle(args, &mut context);
+        let (transitions, failed) = (context.transitions, context.failed);
+        self.transition(transitions);
+        (quit, !failed)
+    }
 }
 
 #[cfg(feature = "async")]
@@ -144,45 +316,175 @@ impl<'a, T: Send, M: Display, H: AsyncHandler<T>, I: InputHandler>
         Shell {
             prompt,
             commands: IndexMap::new(),
+            builtins: builtin::defaults(),
             state,
             handler,
             description: String::new(),
             input_handler,
+            abbreviations: false,
+            batch: false,
+            output: Sink::stdout(),
+            error: Sink::stderr(),
+            reporter: Arc::new(DefaultReporter),
+            theme: Theme::default(),
+            #[cfg(feature = "async")]
+            executor: Arc::new(BlockOn),
+            modes: Vec::new(),
+            middleware: Vec::new(),
+            #[cfg(feature = "rustyline")]
+            attachment: helper::Attachment::new(),
         }
     }
 
     /// Starts running the shell
-    pub async fn run_async(&mut self) -> io::Result<()> {
+    pub async fn run_async(&mut self) -> io::Result<()>
+    where
+        I: Send,
+    {
         '_shell: loop {
             // Read a line
-            let line =
-                match self.input_handler.read(&self.prompt.to_string())? {
-                    InputResult::S(line) => line,
-                    InputResult::Interrupted => continue '_shell,
-                    InputResult::EOF => break '_shell,
-                };
+            let line = match self.read_line()? {
+                InputResult::S(line) => line,
+                InputResult::Interrupted => continue '_shell,
+                InputResult::EOF => break '_shell,
+            };
 
             // Runs the line
-            match Self::unescape(line.trim()) {
-                Ok(line) => {
-                    if self
-                        .handler
-                        .handle_async(
-                            line,
-                            &self.commands,
-                            &mut self.state,
-                            &self.description,
-                        )
-                        .await
-                    {
-                        break '_shell;
-                    }
-                }
-                Err(e) => eprintln!("{}", Paint::red(e.to_string())),
+            let (mut output, mut error) =
+                (self.output.clone(), self.error.clone());
+            let theme = self.theme.resolve(self.output.is_terminal());
+            let error_theme = self.theme.resolve(self.error.is_terminal());
+            let width = self.output.width();
+            let (quit, _) = self
+                .run_line_async(
+                    &line,
+                    &mut output,
+                    &mut error,
+                    &theme,
+                    &error_theme,
+                    width,
+                )
+                .await;
+            let _ = self.output.flush();
+            let _ = self.error.flush();
+            if quit {
+                break '_shell;
             }
         }
         Ok(())
     }
+
+    /// Runs a single line as if it had been entered, without reading from
+    /// the input handler, and returns what it wrote. Questions, such as
+    /// confirmations, are still asked through the input handler unless
+    /// the shell is in [`batch`](Self::batch) mode.
+    pub async fn execute_async(&mut self, line: &str) -> Outcome
+    where
+        I: Send,
+    {
+        let (mut output, mut error) = (Vec::new(), Vec::new());
+        let theme = self.theme.resolve(false);
+        let (quit, success) = self
+            .run_line_async(
+                line,
+                &mut output,
+                &mut error,
+                &theme,
+                &theme,
+                DEFAULT_WIDTH,
+            )
+            .await;
+        Outcome::new(quit, success, output, error)
+    }
+
+    /// Runs a line, returning whether the shell should quit and whether the
+    /// line succeeded.
+    async fn run_line_async(
+        &mut self,
+        line: &str,
+        output: &mut (dyn Write + Send),
+        error: &mut (dyn Write + Send),
+        theme: &Theme,
+        error_theme: &Theme,
+        width: usize,
+    ) -> (bool, bool)
+    where
+        I: Send,
+    {
+        let mut args = match unescape(line.trim()) {
+            Ok(args) => args,
+            Err(e) => {
+                let _ =
+                    self.reporter.invalid_line(error, error_theme, line, &e);
+                return (false, false);
+            }
+        };
+        if !self.expand_abbreviation(&mut args, error, error_theme) {
+            return (false, false);
+        }
+
+        let commands = match self.modes.last_mut() {
+            Some(mode) => &mut mode.commands,
+            None => &mut self.commands,
+        };
+        let mut context = Context {
+            builtins: &self.builtins,
+            commands,
+            state: &mut self.state,
+            description: &self.description,
+            middleware: &self.middleware,
+            line: line.trim(),
+            output,
+            error,
+            input_handler: Input::new(&mut self.input_handler),
+            batch: self.batch,
+            abbreviations: self.abbreviations,
+            reporter: &*self.reporter,
+            theme,
+            error_theme,
+            width,
+            #[cfg(feature = "async")]
+            executor: &*self.executor,
+            transitions: Vec::new(),
+            failed: false,
+        };
+        let quit = self.handler.handle_async(args, &mut context).await;
+        let (transitions, failed) = (context.transitions, context.failed);
+        self.transition(transitions);
+        (quit, !failed)
+    }
+}
+
+/// What running a line with [`execute`](Shell::execute) did.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Outcome {
+    /// Whether the line asked the shell to quit.
+    pub quit: bool,
+    /// Whether the line was run without anything going wrong. It didn't if
+    /// it couldn't be understood, or if a command couldn't be found or
+    /// failed. Errors a command only writes don't count.
+    pub success: bool,
+    /// What was written to the output.
+    pub output: String,
+    /// What was written to the errors, such as a command failing.
+    pub error: String,
+}
+
+impl Outcome {
+    /// Creates an outcome from what was written.
+    pub(crate) fn new(
+        quit: bool,
+        success: bool,
+        output: Vec<u8>,
+        error: Vec<u8>,
+    ) -> Self {
+        Self {
+            quit,
+            success,
+            output: String::from_utf8_lossy(&output).into_owned(),
+            error: String::from_utf8_lossy(&error).into_owned(),
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -194,50 +496,307 @@ pub enum UnescapeError {
 }
 
 impl<'a, T, M: Display, H, I: InputHandler> Shell<'a, T, M, H, I> {
-    /// Unescapes a line and gets the arguments.
-    fn unescape(command: &str) -> Result<Vec<String>, UnescapeError> {
-        // Create a vec to store the split int.
-        let mut vec = vec![String::new()];
-
-        // Are we in an escape sequence?
-        let mut escape = false;
-
-        // Are we in a string?
-        let mut string = false;
-
-        // Go through each char in the string
-        for c in command.chars() {
-            let segment = vec.last_mut().unwrap();
-            if escape {
-                match c {
-                    '\\' => segment.push('\\'),
-                    ' ' if !string => segment.push(' '),
-                    'n' => segment.push('\n'),
-                    'r' => segment.push('\r'),
-                    't' => segment.push('\t'),
-                    '"' => segment.push('"'),
-                    _ => return Err(UnescapeError::UnhandledEscapeSequence(c)),
+    /// Reads a line from the input handler, lending the commands to any
+    /// helpers whilst doing so.
+    fn read_line(&mut self) -> io::Result<InputResult> {
+        let theme = self.theme.resolve(self.output.is_terminal());
+        #[cfg(feature = "rustyline")]
+        let session = helper::Session {
+            builtins: &self.builtins,
+            commands: match self.modes.last() {
+                Some(mode) => &mode.commands,
+                None => &self.commands,
+            },
+            state: &self.state,
+            abbreviations: self.abbreviations,
+            theme,
+        };
+
+        let prompt = match self.modes.last() {
+            Some(mode) => mode.prompt.clone(),
+            None => self.prompt.to_string(),
+        };
+        let prompt = theme.prompt.paint(prompt).to_string();
+        let input_handler = &mut self.input_handler;
+        #[cfg(feature = "rustyline")]
+        return self
+            .attachment
+            .lend(&session, || input_handler.read(&prompt));
+        #[cfg(not(feature = "rustyline"))]
+        input_handler.read(&prompt)
+    }
+
+    /// The commands of the active mode, or the shell's if there is none.
+    fn active_commands(&self) -> &Commands<'a, T> {
+        match self.modes.last() {
+            Some(mode) => &mode.commands,
+            None => &self.commands,
+        }
+    }
+
+    /// Makes the changes to the modes asked for by a command.
+    fn transition(&mut self, transitions: Vec<Transition<'a, T>>) {
+        for transition in transitions {
+            match transition {
+                Transition::Push(mode) => self.modes.push(mode),
+                Transition::Pop => {
+                    self.modes.pop();
                 }
-                escape = false;
-            } else {
-                match c {
-                    '\\' => escape = true,
-                    '"' => string = !string,
-                    ' ' if string => segment.push(c),
-                    ' ' if !string => vec.push(String::new()),
-                    _ => segment.push(c),
+            }
+        }
+    }
+
+    /// Replaces an abbreviated command name with the full name, if
+    /// abbreviations are turned on.
+    ///
+    /// Returns false if the abbreviation is ambiguous, in which case the
+    /// candidates are reported.
+    fn expand_abbreviation(
+        &self,
+        line: &mut [String],
+        error: &mut dyn Write,
+        theme: &Theme,
+    ) -> bool {
+        let name = match line.first_mut() {
+            Some(name) if self.abbreviations => name,
+            _ => return true,
+        };
+
+        let candidates = self
+            .builtins
+            .keys()
+            .map(|name| &**name)
+            .chain(self.active_commands().keys().map(|name| &**name));
+        match suggest::expand(name, candidates) {
+            Abbreviation::Unique(full) => *name = full.to_string(),
+            Abbreviation::Ambiguous(candidates) => {
+                let _ =
+                    self.reporter.ambiguous(error, theme, name, &candidates);
+                return false;
+            }
+            Abbreviation::Unknown => (),
+        }
+        true
+    }
+}
+
+/// What a piece of a line is, as far as the shell is concerned.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Lexeme {
+    /// A plain character, which may be within quotes.
+    Char(char),
+    /// An escape sequence, and the character it stands for.
+    Escape(char),
+    /// An escape sequence which is not understood.
+    InvalidEscape(char),
+    /// A `"`, which opens or closes a string.
+    Quote,
+    /// A space between two arguments.
+    Separator,
+}
+
+/// A piece of a line, along with where it is and which argument it is part
+/// of.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Piece {
+    pub(crate) lexeme: Lexeme,
+    pub(crate) span: Range<usize>,
+    pub(crate) argument: usize,
+    /// Whether this is between quotes (quotes themselves are not).
+    pub(crate) quoted: bool,
+}
+
+/// Splits a line into pieces. The second value is whether the line ends
+/// within quotes.
+///
+/// This is the shell's tokenizer; [`unescape`] and anything that needs to
+/// agree with it (such as syntax highlighting) are built on top of it.
+pub(crate) fn lex(command: &str) -> (Vec<Piece>, bool) {
+    let mut pieces = Vec::new();
+
+    // Which argument we are in
+    let mut argument = 0;
+
+    // Are we in an escape sequence? (and where did it start)
+    let mut escape = None;
+
+    // Are we in a string?
+    let mut string = false;
+
+    // Go through each char in the string
+    for (i, c) in command.char_indices() {
+        let end = i + c.len_utf8();
+        if let Some(start) = escape.take() {
+            let lexeme = match c {
+                '\\' => Lexeme::Escape('\\'),
+                ' ' if !string => Lexeme::Escape(' '),
+                'n' => Lexeme::Escape('\n'),
+                'r' => Lexeme::Escape('\r'),
+                't' => Lexeme::Escape('\t'),
+                '"' => Lexeme::Escape('"'),
+                _ => Lexeme::InvalidEscape(c),
+            };
+            pieces.push(Piece {
+                lexeme,
+                span: start..end,
+                argument,
+                quoted: string,
+            });
+        } else {
+            let lexeme = match c {
+                '\\' => {
+                    escape = Some(i);
+                    continue;
                 }
+                '"' => Lexeme::Quote,
+                ' ' if !string => Lexeme::Separator,
+                _ => Lexeme::Char(c),
+            };
+            if lexeme == Lexeme::Quote {
+                string = !string;
+            }
+            pieces.push(Piece {
+                lexeme,
+                span: i..end,
+                argument,
+                quoted: string && lexeme != Lexeme::Quote,
+            });
+            if lexeme == Lexeme::Separator {
+                argument += 1;
             }
         }
+    }
+
+    (pieces, string)
+}
+
+/// Unescapes a line and gets the arguments.
+pub(crate) fn unescape(command: &str) -> Result<Vec<String>, UnescapeError> {
+    // Create a vec to store the split int.
+    let mut vec = vec![String::new()];
 
-        if string {
-            return Err(UnescapeError::UnclosedQuotes);
+    let (pieces, string) = lex(command);
+    for piece in pieces {
+        let segment = vec.last_mut().unwrap();
+        match piece.lexeme {
+            Lexeme::Char(c) | Lexeme::Escape(c) => segment.push(c),
+            Lexeme::InvalidEscape(c) => {
+                return Err(UnescapeError::UnhandledEscapeSequence(c))
+            }
+            Lexeme::Quote => (),
+            Lexeme::Separator => vec.push(String::new()),
         }
+    }
+
+    if string {
+        return Err(UnescapeError::UnclosedQuotes);
+    }
+
+    if vec.len() == 1 && vec[0].is_empty() {
+        vec.clear();
+    }
+
+    Ok(vec)
+}
 
-        if vec.len() == 1 && vec[0].is_empty() {
-            vec.clear();
+/// Escapes an argument so that [`unescape`] turns it back into itself.
+pub(crate) fn escape(argument: &str) -> String {
+    let mut escaped = String::with_capacity(argument.len());
+    for c in argument.chars() {
+        match c {
+            '\\' | ' ' | '"' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
         }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        unescape(line).unwrap()
+    }
+
+    #[test]
+    fn splits_on_spaces_outside_quotes() {
+        assert_eq!(args("greet Alice"), ["greet", "Alice"]);
+        assert_eq!(
+            args("greet \"Alice Smith\" x"),
+            ["greet", "Alice Smith", "x"]
+        );
+        assert_eq!(args("say a\"b c\"d"), ["say", "ab cd"]);
+        assert_eq!(args("a  b"), ["a", "", "b"]);
+        assert_eq!(args("a \"\""), ["a", ""]);
+        assert!(args("").is_empty());
+    }
+
+    #[test]
+    fn unescapes_escape_sequences() {
+        assert_eq!(
+            args(r#"a\ b \\ \"q\" \n\r\t"#),
+            ["a b", "\\", "\"q\"", "\n\r\t"]
+        );
+        assert_eq!(args(r#""\"\\""#), ["\"\\"]);
+    }
 
-        Ok(vec)
+    #[test]
+    fn rejects_invalid_lines() {
+        assert!(matches!(
+            unescape(r"a \q"),
+            Err(UnescapeError::UnhandledEscapeSequence('q'))
+        ));
+        assert!(matches!(
+            unescape(r#""a\ b""#),
+            Err(UnescapeError::UnhandledEscapeSequence(' '))
+        ));
+        assert!(matches!(
+            unescape("a \"b"),
+            Err(UnescapeError::UnclosedQuotes)
+        ));
+    }
+
+    #[test]
+    fn lexes_spans_and_arguments() {
+        let (pieces, unclosed) = lex("a \"b\\n");
+        assert!(unclosed);
+        let pieces: Vec<_> = pieces
+            .into_iter()
+            .map(|piece| {
+                (piece.lexeme, piece.span, piece.argument, piece.quoted)
+            })
+            .collect();
+        assert_eq!(
+            pieces,
+            [
+                (Lexeme::Char('a'), 0..1, 0, false),
+                (Lexeme::Separator, 1..2, 0, false),
+                (Lexeme::Quote, 2..3, 1, false),
+                (Lexeme::Char('b'), 3..4, 1, true),
+                (Lexeme::Escape('\n'), 4..6, 1, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_round_trip() {
+        let arguments = [
+            "plain",
+            "two words",
+            "\"quoted\"",
+            "back\\slash",
+            "tab\tnew\n",
+            "日本語",
+        ];
+        for argument in arguments.iter() {
+            assert_eq!(args(&escape(argument)), [*argument]);
+        }
     }
 }
@@ -0,0 +1,114 @@
+//! # Sinks
+//!
+//! A sink is where a [`Shell`](crate::Shell) or [`App`](crate::App) writes
+//! its output or errors. By default these are the standard output and
+//! error, but any writer can be used, such as a file or a network
+//! connection.
+//!
+//! ```rust
+//! use shellfish::sink::Sink;
+//! use shellfish::Shell;
+//! use std::fs::File;
+//!
+//! let mut shell = Shell::new((), "[Shell]-$");
+//! # let path = std::env::temp_dir().join("shellfish-sink-example.log");
+//! shell.error = Sink::new(File::create(&path).unwrap());
+//! ```
+//!
+//! Sinks are shared between their clones, so cloning a shell keeps it
+//! writing to the same place.
+
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use terminal_size::{terminal_size, terminal_size_of, Width};
+
+use crate::help::DEFAULT_WIDTH;
+
+/// A shared writer for output or errors. See the
+/// [module level documentation](self).
+#[derive(Clone)]
+pub struct Sink {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    terminal: bool,
+}
+
+impl Sink {
+    /// Creates a sink which writes to `writer`.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self::from_box(Box::new(writer))
+    }
+
+    /// Creates a sink from a boxed writer.
+    pub fn from_box(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(writer)),
+            terminal: false,
+        }
+    }
+
+    /// A sink which writes to the standard output.
+    pub fn stdout() -> Self {
+        Self {
+            terminal: io::stdout().is_terminal(),
+            ..Self::new(io::stdout())
+        }
+    }
+
+    /// A sink which writes to the standard error.
+    pub fn stderr() -> Self {
+        Self {
+            terminal: io::stderr().is_terminal(),
+            ..Self::new(io::stderr())
+        }
+    }
+
+    /// Whether this sink writes to a terminal, in which case colours are
+    /// used by default. Only the standard output and error can be.
+    pub fn is_terminal(&self) -> bool {
+        self.terminal
+    }
+
+    /// The width of this sink in columns, which help is wrapped to. This
+    /// is the `COLUMNS` environment variable if it is set, then the width
+    /// of the terminal, falling back to [`DEFAULT_WIDTH`].
+    pub fn width(&self) -> usize {
+        if let Some(columns) = env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+        {
+            return columns;
+        }
+        let size = match self.terminal {
+            true => terminal_size().or_else(|| terminal_size_of(io::stderr())),
+            false => None,
+        };
+        size.map_or(DEFAULT_WIDTH, |(Width(width), _)| width.into())
+    }
+
+    /// Locks the writer. A lock poisoned by a panic is still used, as
+    /// losing the output would be worse.
+    fn lock(&self) -> MutexGuard<'_, Box<dyn Write + Send>> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl fmt::Debug for Sink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sink")
+            .field("terminal", &self.terminal)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,145 @@
+//! Matching of misspelt and abbreviated command names.
+
+/// Finds the closest of the candidates to the name, if it is within the
+/// threshold. A threshold of `0` disables suggestions.
+pub(crate) fn did_you_mean<'c>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'c str>,
+    threshold: usize,
+) -> Option<&'c str> {
+    if threshold == 0 {
+        return None;
+    }
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// What an abbreviated command name stands for.
+pub(crate) enum Abbreviation<'c> {
+    /// It is a prefix of exactly one name, or is a name itself.
+    Unique(&'c str),
+    /// It is a prefix of all of these names.
+    Ambiguous(Vec<&'c str>),
+    /// It isn't a prefix of any name.
+    Unknown,
+}
+
+/// Works out what command name a prefix stands for. An empty prefix
+/// doesn't stand for anything.
+pub(crate) fn expand<'c>(
+    prefix: &str,
+    candidates: impl IntoIterator<Item = &'c str>,
+) -> Abbreviation<'c> {
+    if prefix.is_empty() {
+        return Abbreviation::Unknown;
+    }
+
+    let mut matches: Vec<&str> = Vec::new();
+    for candidate in candidates {
+        if candidate == prefix {
+            return Abbreviation::Unique(candidate);
+        }
+        if candidate.starts_with(prefix) && !matches.contains(&candidate) {
+            matches.push(candidate);
+        }
+    }
+
+    match matches.len() {
+        0 => Abbreviation::Unknown,
+        1 => Abbreviation::Unique(matches[0]),
+        _ => Abbreviation::Ambiguous(matches),
+    }
+}
+
+/// The Levenshtein distance between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 4] = ["greet", "grep", "quit", "help"];
+
+    #[test]
+    fn measures_edits() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("greet", "greet"), 0);
+        assert_eq!(distance("", "quit"), 4);
+        assert_eq!(distance("gret", "greet"), 1);
+        assert_eq!(distance("greta", "greet"), 2);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("naïve", "naive"), 1);
+    }
+
+    #[test]
+    fn suggests_the_closest_name_within_the_threshold() {
+        assert_eq!(
+            did_you_mean("gret", NAMES.iter().copied(), 2),
+            Some("greet")
+        );
+        assert_eq!(
+            did_you_mean("qiut", NAMES.iter().copied(), 2),
+            Some("quit")
+        );
+        assert_eq!(did_you_mean("launch", NAMES.iter().copied(), 2), None);
+        assert_eq!(did_you_mean("gret", NAMES.iter().copied(), 0), None);
+    }
+
+    #[test]
+    fn expands_unique_prefixes() {
+        let names = NAMES.iter().copied();
+        assert!(matches!(
+            expand("gree", names),
+            Abbreviation::Unique("greet")
+        ));
+        let names = NAMES.iter().copied();
+        assert!(matches!(expand("q", names), Abbreviation::Unique("quit")));
+        let names = NAMES.iter().copied();
+        assert!(matches!(expand("launch", names), Abbreviation::Unknown));
+    }
+
+    #[test]
+    fn expands_empty_prefixes_to_nothing() {
+        let names = NAMES.iter().copied();
+        assert!(matches!(expand("", names), Abbreviation::Unknown));
+    }
+
+    #[test]
+    fn prefers_exact_names_to_prefixes() {
+        let names = ["grep", "grep-all"].iter().copied();
+        assert!(matches!(
+            expand("grep", names),
+            Abbreviation::Unique("grep")
+        ));
+    }
+
+    #[test]
+    fn lists_the_names_an_ambiguous_prefix_could_be() {
+        let names = NAMES.iter().copied().chain(Some("greet"));
+        match expand("gr", names) {
+            Abbreviation::Ambiguous(names) => {
+                assert_eq!(names, ["greet", "grep"])
+            }
+            _ => panic!("`gr` should be ambiguous"),
+        }
+    }
+}
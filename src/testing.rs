@@ -0,0 +1,494 @@
+//! # Testing
+//!
+//! Helpers for testing shells without spawning a process. Lines are fed to
+//! a [`Shell`] or [`App`](crate::App) with [`Feed::feed`], which captures
+//! what they write, and the [`Outcome`] can then be checked with its
+//! assertions. Any questions asked, such as confirmations, are answered
+//! from a [`Script`].
+//!
+//! Only output written through the [`Context`](crate::Context) is
+//! captured, so commands which are tested this way should be created with
+//! [`Command::new_with_context`](crate::Command::new_with_context) and
+//! write to its [`output`](crate::Context::output) rather than using
+//! `println!`. Commands created with [`Command::new`](crate::Command::new)
+//! aren't given the context, so their output can't be captured; only
+//! whether they fail can be checked.
+//!
+//! ```rust
+//! use shellfish::testing::{self, Feed};
+//! use shellfish::{Command, Context};
+//! use std::error::Error;
+//! use std::io::Write;
+//!
+//! fn count(
+//!     context: &mut Context<'_, '_, u64>,
+//!     _args: Vec<String>,
+//! ) -> Result<(), Box<dyn Error>> {
+//!     *context.state += 1;
+//!     writeln!(context.output, "Called {} times", context.state)?;
+//!     Ok(())
+//! }
+//!
+//! let mut shell = testing::shell(0);
+//! shell.commands.insert(
+//!     "count".into(),
+//!     Command::new_with_context("counts.".to_string(), count),
+//! );
+//!
+//! shell.feed("count").assert_stdout_contains("1 times");
+//! shell.feed("count").assert_success();
+//! shell.feed("cuont").assert_stderr_contains("Command not found: cuont");
+//! shell.feed("quit").assert_quit();
+//! ```
+//!
+//! Asynchronous shells can be tested in the same way, by checking the
+//! outcome of [`Shell::execute_async`].
+//!
+//! ## Transcripts
+//!
+//! A transcript is a file of lines to run, each starting with `$ `, and
+//! what each writes below it, errors after output:
+//!
+//! ```text
+//! $ count
+//! Called 1 times
+//! $ cuont
+//! Command not found: cuont
+//! did you mean `count`?
+//! ```
+//!
+//! [`check_transcript`] runs each line and panics with the differences if
+//! any output doesn't match. Blank lines around the output of a line and
+//! colours are ignored. When the `SHELLFISH_TRANSCRIPTS` environment
+//! variable is set to `overwrite`, the file is rewritten with what was
+//! written instead, which is useful when the output changes on purpose.
+
+use std::collections::VecDeque;
+use std::env;
+use std::fmt::{Display, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::handler::DefaultHandler;
+use crate::input_handler::InputResult;
+use crate::shell::Outcome;
+use crate::{Handler, InputHandler, Shell};
+
+/// An [`InputHandler`] which reads from a list of lines, then reaches the
+/// end of its input.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Script {
+    lines: VecDeque<String>,
+    history: Vec<String>,
+}
+
+impl Script {
+    /// Creates a new script from some lines.
+    pub fn new<S: Into<String>>(lines: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            lines: lines.into_iter().map(Into::into).collect(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Adds a line to the end of the script.
+    pub fn push(&mut self, line: impl Into<String>) {
+        self.lines.push_back(line.into());
+    }
+
+    /// Whether every line has been read.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+impl InputHandler for Script {
+    fn read(&mut self, _prompt: &str) -> io::Result<InputResult> {
+        Ok(match self.lines.pop_front() {
+            Some(line) => {
+                self.history.push(line.clone());
+                InputResult::S(line)
+            }
+            None => InputResult::EOF,
+        })
+    }
+
+    fn history(&self) -> Vec<String> {
+        self.history.clone()
+    }
+}
+
+/// Creates a shell with the default handler which reads from an empty
+/// [`Script`], for feeding lines to.
+pub fn shell<'a, T>(
+    state: T,
+) -> Shell<'a, T, &'static str, DefaultHandler, Script> {
+    Shell::new_with_handler(
+        state,
+        "$ ",
+        DefaultHandler::default(),
+        Script::default(),
+    )
+}
+
+/// Runs lines and captures what they write.
+pub trait Feed {
+    /// Runs a line, returning what it wrote.
+    fn feed(&mut self, line: &str) -> Outcome;
+}
+
+impl<T, M, H, I> Feed for Shell<'_, T, M, H, I>
+where
+    M: Display,
+    H: Handler<T>,
+    I: InputHandler,
+{
+    fn feed(&mut self, line: &str) -> Outcome {
+        self.execute(line)
+    }
+}
+
+#[cfg(feature = "app")]
+impl<T, H> Feed for crate::App<'_, T, H>
+where
+    T: serde::Serialize + for<'a> serde::Deserialize<'a>,
+    H: crate::handler::CommandLineHandler + Handler<T>,
+{
+    fn feed(&mut self, line: &str) -> Outcome {
+        self.execute(line)
+    }
+}
+
+/// Assertions on what a line wrote, for tests. Colours are ignored when
+/// comparing text.
+impl Outcome {
+    /// Panics unless the output contains `text`.
+    #[track_caller]
+    pub fn assert_stdout_contains(&self, text: &str) -> &Self {
+        let output = strip_colours(&self.output);
+        assert!(
+            output.contains(text),
+            "expected the output to contain {:?}, but it was:\n{}",
+            text,
+            output
+        );
+        self
+    }
+
+    /// Panics unless the errors contain `text`.
+    #[track_caller]
+    pub fn assert_stderr_contains(&self, text: &str) -> &Self {
+        let error = strip_colours(&self.error);
+        assert!(
+            error.contains(text),
+            "expected the errors to contain {:?}, but they were:\n{}",
+            text,
+            error
+        );
+        self
+    }
+
+    /// Panics unless the output, ignoring surrounding whitespace, is
+    /// `text`.
+    #[track_caller]
+    pub fn assert_stdout_eq(&self, text: &str) -> &Self {
+        assert_eq!(strip_colours(&self.output).trim(), text.trim());
+        self
+    }
+
+    /// Panics unless the line [succeeded](Outcome::success). Anything
+    /// else written to the errors, such as a warning, is allowed.
+    #[track_caller]
+    pub fn assert_success(&self) -> &Self {
+        assert!(
+            self.success,
+            "expected the line to succeed, but it failed with:\n{}",
+            strip_colours(&self.error)
+        );
+        self
+    }
+
+    /// Panics if the line [succeeded](Outcome::success).
+    #[track_caller]
+    pub fn assert_failure(&self) -> &Self {
+        assert!(!self.success, "expected the line to fail, but it succeeded");
+        self
+    }
+
+    /// Panics unless the line asked the shell to quit.
+    #[track_caller]
+    pub fn assert_quit(&self) -> &Self {
+        assert!(self.quit, "expected the shell to quit");
+        self
+    }
+}
+
+/// Removes the ANSI escape sequences which colour text.
+pub fn strip_colours(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip up to and including the final byte of the sequence
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// The environment variable which, when set to `overwrite`, makes
+/// [`check_transcript`] rewrite transcripts rather than check them.
+pub const TRANSCRIPTS_VAR: &str = "SHELLFISH_TRANSCRIPTS";
+
+/// Runs the lines of a transcript file and checks their output, see the
+/// [module level documentation](self#transcripts).
+///
+/// # Panics
+///
+/// If the file can't be read or written, or if any output differs.
+#[track_caller]
+pub fn check_transcript(shell: &mut impl Feed, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let transcript = fs::read_to_string(path).unwrap_or_else(|e| {
+        panic!("couldn't read transcript {}: {}", path.display(), e)
+    });
+
+    // Run each line
+    let mut actual = String::new();
+    let mut differences = String::new();
+    for (line, expected) in parse_transcript(&transcript) {
+        let outcome = shell.feed(line);
+        let output = normalise(&format!("{}{}", outcome.output, outcome.error));
+        let _ = writeln!(actual, "$ {}", line);
+        for output in &output {
+            let _ = writeln!(actual, "{}", output);
+        }
+
+        if output != expected {
+            let _ = writeln!(differences, "$ {}", line);
+            for expected in &expected {
+                let _ = writeln!(differences, "- {}", expected);
+            }
+            for output in &output {
+                let _ = writeln!(differences, "+ {}", output);
+            }
+        }
+    }
+
+    if matches!(env::var(TRANSCRIPTS_VAR).as_deref(), Ok("overwrite")) {
+        fs::write(path, actual).unwrap_or_else(|e| {
+            panic!("couldn't write transcript {}: {}", path.display(), e)
+        });
+    } else if !differences.is_empty() {
+        panic!(
+            "transcript {} differs (set {}=overwrite to update it):\n{}",
+            path.display(),
+            TRANSCRIPTS_VAR,
+            differences
+        );
+    }
+}
+
+/// Splits a transcript into its lines to run and their expected output.
+fn parse_transcript(transcript: &str) -> Vec<(&str, Vec<String>)> {
+    let mut entries: Vec<(&str, Vec<String>)> = Vec::new();
+    let mut output = String::new();
+    for line in transcript.lines() {
+        if let Some(command) = line.strip_prefix("$ ") {
+            if let Some((_, expected)) = entries.last_mut() {
+                *expected = normalise(&output);
+            }
+            output.clear();
+            entries.push((command, Vec::new()));
+        } else {
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    if let Some((_, expected)) = entries.last_mut() {
+        *expected = normalise(&output);
+    }
+    entries
+}
+
+/// Splits output into lines, without colours, trailing whitespace or blank
+/// lines around it.
+fn normalise(output: &str) -> Vec<String> {
+    let stripped = strip_colours(output);
+    let lines: Vec<_> = stripped.lines().map(str::trim_end).collect();
+    let start = lines.iter().position(|line| !line.is_empty());
+    let end = lines.iter().rposition(|line| !line.is_empty());
+    match (start, end) {
+        (Some(start), Some(end)) => lines[start..=end]
+            .iter()
+            .map(|line| line.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::DefaultHandler;
+    use crate::input_handler::InputResult;
+    use crate::{Command, Context};
+    use std::error::Error;
+    use std::marker::PhantomData;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::process;
+    use std::rc::Rc;
+
+    fn warn(
+        context: &mut Context<'_, '_, ()>,
+        _args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        writeln!(context.error, "this is deprecated")?;
+        Ok(())
+    }
+
+    #[test]
+    fn tells_failures_from_warnings() {
+        let mut shell = shell(());
+        shell.commands.insert(
+            "warn".into(),
+            Command::new_with_context("warns.".to_string(), warn),
+        );
+        shell.commands.insert(
+            "fail".into(),
+            Command::new("fails.".to_string(), |_, _| Err("failed".into())),
+        );
+
+        let outcome = shell.feed("warn");
+        outcome
+            .assert_success()
+            .assert_stderr_contains("deprecated");
+        shell.feed("fail").assert_failure();
+        shell.feed("missing").assert_failure();
+        shell.feed("\\q").assert_failure();
+        shell.feed("help").assert_success();
+    }
+
+    /// An input handler which isn't `Send`.
+    struct Local(PhantomData<Rc<()>>);
+
+    impl InputHandler for Local {
+        fn read(&mut self, _prompt: &str) -> io::Result<InputResult> {
+            Ok(InputResult::EOF)
+        }
+    }
+
+    #[test]
+    fn feeds_shells_whose_input_handlers_arent_send() {
+        let mut shell = Shell::new_with_handler(
+            (),
+            "$ ",
+            DefaultHandler::default(),
+            Local(PhantomData),
+        );
+        shell.feed("help").assert_stdout_contains("quit");
+    }
+
+    #[test]
+    fn parses_lines_and_their_output() {
+        let transcript = "$ count\nCalled 1 times\n$ quit\n$ cuont\n\
+                          Command not found: cuont\ndid you mean `count`?\n";
+        assert_eq!(
+            parse_transcript(transcript),
+            [
+                ("count", vec!["Called 1 times".to_string()]),
+                ("quit", vec![]),
+                (
+                    "cuont",
+                    vec![
+                        "Command not found: cuont".to_string(),
+                        "did you mean `count`?".to_string(),
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_blank_lines_around_output_and_colours() {
+        let transcript = "\n$ help\n\n\x1b[1mCommands:\x1b[0m  \n\n  quit\n\n";
+        assert_eq!(
+            parse_transcript(transcript),
+            [(
+                "help",
+                vec![
+                    "Commands:".to_string(),
+                    String::new(),
+                    "  quit".to_string()
+                ]
+            )]
+        );
+    }
+
+    fn count(
+        context: &mut Context<'_, '_, u64>,
+        _args: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        *context.state += 1;
+        writeln!(context.output, "Called {} times", context.state)?;
+        Ok(())
+    }
+
+    #[test]
+    fn checks_and_overwrites_transcripts() {
+        let path = env::temp_dir()
+            .join(format!("shellfish-transcript-{}.txt", process::id()));
+        let transcript = "$ count\nCalled 1 times\n$ count\nCalled 3 times\n";
+        fs::write(&path, transcript).unwrap();
+        let shell = || {
+            let mut shell = shell(0);
+            shell.commands.insert(
+                "count".into(),
+                Command::new_with_context("counts.".to_string(), count),
+            );
+            shell
+        };
+
+        // Only the differing line is shown
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+            check_transcript(&mut shell(), &path)
+        }));
+        let message = *panicked.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("SHELLFISH_TRANSCRIPTS=overwrite"));
+        assert!(
+            message.ends_with("$ count\n- Called 3 times\n+ Called 2 times\n")
+        );
+
+        env::set_var(TRANSCRIPTS_VAR, "overwrite");
+        check_transcript(&mut shell(), &path);
+        env::remove_var(TRANSCRIPTS_VAR);
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            written,
+            "$ count\nCalled 1 times\n$ count\nCalled 2 times\n"
+        );
+
+        // The rewritten transcript passes
+        check_transcript(&mut shell(), &path);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ignores_text_before_the_first_line() {
+        assert_eq!(
+            parse_transcript("Counting\n$ count\n1"),
+            [("count", vec!["1".to_string()])]
+        );
+        assert!(parse_transcript("").is_empty());
+        assert!(parse_transcript("$count\n").is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+//! # Themes
+//!
+//! A theme sets the colours a [`Shell`](crate::Shell) or
+//! [`App`](crate::App) uses, from errors to the prompt. Whether colours are
+//! used at all is decided by its [`colour`](Theme::colour): by default they
+//! are only used when writing to a terminal, and the `NO_COLOR` and
+//! `CLICOLOR_FORCE` environment variables are respected. The output and
+//! the errors are checked apart, so errors are still coloured when the
+//! output is piped.
+//!
+//! ```rust
+//! use shellfish::theme::{Color, ColourChoice, Style, Theme};
+//! use shellfish::Shell;
+//!
+//! let mut shell = Shell::new((), "[Shell]-$");
+//! shell.theme = Theme {
+//!     error: Style::new(Color::Magenta),
+//!     prompt: Style::new(Color::Blue).bold(),
+//!     ..Theme::default()
+//! };
+//!
+//! // Or turn colours off entirely
+//! shell.theme.colour = ColourChoice::Never;
+//! ```
+
+use std::env;
+use std::ffi::OsString;
+
+pub use yansi::{Color, Style};
+
+/// When colours are used.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ColourChoice {
+    /// Only when writing to a terminal, unless `NO_COLOR` is set to turn
+    /// them off or `CLICOLOR_FORCE` is set to turn them on.
+    #[default]
+    Auto,
+    /// Always.
+    Always,
+    /// Never.
+    Never,
+}
+
+impl ColourChoice {
+    /// Whether to use colours, given whether the output is a terminal.
+    pub fn enabled(self, terminal: bool) -> bool {
+        self.enabled_with(terminal, |name| env::var_os(name))
+    }
+
+    /// Like [`enabled`](Self::enabled), reading the environment variables
+    /// with `var`.
+    fn enabled_with(
+        self,
+        terminal: bool,
+        var: impl Fn(&str) -> Option<OsString>,
+    ) -> bool {
+        match self {
+            ColourChoice::Always => true,
+            ColourChoice::Never => false,
+            ColourChoice::Auto => {
+                let no_color = var("NO_COLOR").unwrap_or_default();
+                let force = var("CLICOLOR_FORCE").unwrap_or_default();
+                if !no_color.is_empty() {
+                    false
+                } else if !force.is_empty() && force != "0" {
+                    true
+                } else {
+                    terminal
+                }
+            }
+        }
+    }
+}
+
+/// The colours of a shell. See the [module level documentation](self).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Theme {
+    /// Errors.
+    pub error: Style,
+    /// The `error:` label of [diagnostics](crate::Diagnostic).
+    pub error_label: Style,
+    /// Commands which don't exist, whilst typing.
+    pub unknown: Style,
+    /// Mistakes in the line whilst it is typed, such as invalid escape
+    /// sequences and unclosed strings.
+    pub invalid: Style,
+    /// Headings in the help, and labels such as `help:`.
+    pub heading: Style,
+    /// The names of commands, in the help and whilst typing.
+    pub command: Style,
+    /// The prompt.
+    pub prompt: Style,
+    /// Anything less important, such as unavailable commands and hints.
+    pub muted: Style,
+    /// Strings whilst typing.
+    pub string: Style,
+    /// Escape sequences whilst typing.
+    pub escape: Style,
+    /// When the colours are used.
+    pub colour: ColourChoice,
+}
+
+impl Theme {
+    /// A theme without any colours.
+    pub fn plain() -> Self {
+        Self {
+            error: Style::default(),
+            error_label: Style::default(),
+            unknown: Style::default(),
+            invalid: Style::default(),
+            heading: Style::default(),
+            command: Style::default(),
+            prompt: Style::default(),
+            muted: Style::default(),
+            string: Style::default(),
+            escape: Style::default(),
+            colour: ColourChoice::Never,
+        }
+    }
+
+    /// Gets the theme to use, which is plain if colours shouldn't be used.
+    pub fn resolve(&self, terminal: bool) -> Self {
+        match self.colour.enabled(terminal) {
+            true => *self,
+            false => Self::plain(),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            error: Style::new(Color::Red),
+            error_label: Style::new(Color::Red).bold(),
+            unknown: Style::new(Color::Red).bold(),
+            invalid: Style::new(Color::Red).underline(),
+            heading: Style::default().bold(),
+            command: Style::new(Color::Green).bold(),
+            prompt: Style::default(),
+            muted: Style::default().dimmed(),
+            string: Style::new(Color::Yellow),
+            escape: Style::new(Color::Cyan),
+            colour: ColourChoice::Auto,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::ColourChoice;
+
+    /// Whether colours are used with the given environment variables.
+    fn enabled(
+        choice: ColourChoice,
+        terminal: bool,
+        vars: &[(&str, &str)],
+    ) -> bool {
+        choice.enabled_with(terminal, |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| OsString::from(value))
+        })
+    }
+
+    #[test]
+    fn auto_follows_the_terminal() {
+        assert!(enabled(ColourChoice::Auto, true, &[]));
+        assert!(!enabled(ColourChoice::Auto, false, &[]));
+    }
+
+    #[test]
+    fn no_color_turns_colours_off() {
+        let vars = [("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")];
+        assert!(!enabled(ColourChoice::Auto, true, &vars));
+        assert!(enabled(ColourChoice::Auto, true, &[("NO_COLOR", "")]));
+    }
+
+    #[test]
+    fn clicolor_force_turns_colours_on() {
+        let force = [("CLICOLOR_FORCE", "1")];
+        assert!(enabled(ColourChoice::Auto, false, &force));
+        assert!(!enabled(
+            ColourChoice::Auto,
+            false,
+            &[("CLICOLOR_FORCE", "0")]
+        ));
+        assert!(!enabled(
+            ColourChoice::Auto,
+            false,
+            &[("CLICOLOR_FORCE", "")]
+        ));
+    }
+
+    #[test]
+    fn always_and_never_ignore_the_environment() {
+        let vars = [("NO_COLOR", "1")];
+        assert!(enabled(ColourChoice::Always, false, &vars));
+        assert!(!enabled(
+            ColourChoice::Never,
+            true,
+            &[("CLICOLOR_FORCE", "1")]
+        ));
+    }
+}